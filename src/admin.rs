@@ -0,0 +1,237 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Request, State},
+    http::{StatusCode, header::AUTHORIZATION},
+    middleware::{self, Next},
+    response::Response,
+    routing::{delete, post},
+};
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+
+use crate::{
+    api::DbChannel,
+    error::ApiError,
+    oshi, pages,
+    platform::{Platform, Platforms},
+    sqlite, twitch,
+    youtube::channels,
+};
+
+pub fn router(token: Option<String>) -> Router {
+    Router::new()
+        .route("/channels", post(post_channels))
+        .route("/channels/{channel}", delete(delete_channel))
+        .route("/groups", post(post_groups))
+        .route("/groups/{video_id}", delete(delete_group))
+        .layer(middleware::from_fn_with_state(token, authorize))
+}
+
+/**
+ * Requires `Authorization: Bearer <ADMIN_TOKEN>`, the admin API is hidden when no token is set
+ */
+async fn authorize(
+    State(token): State<Option<String>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let Some(token) = token else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    let authorized = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.strip_prefix("Bearer "))
+        // compared in constant time so the token can't be guessed from response times
+        .is_some_and(|val| bool::from(val.as_bytes().ct_eq(token.as_bytes())));
+
+    if !authorized {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[derive(Deserialize)]
+struct GroupRequest {
+    videos: Vec<String>,
+}
+
+// POST /admin/groups
+async fn post_groups(Json(body): Json<GroupRequest>) -> Result<String, ApiError> {
+    if body.videos.len() < 2 {
+        return Err(ApiError::BadRequest("at least 2 videos are needed".to_string()));
+    }
+
+    for video_id in &body.videos {
+        if sqlite::get_db_video(video_id)?.is_none() {
+            return Err(ApiError::NotFound(format!("video {video_id} not found")));
+        }
+    }
+
+    let group_id = format!("manual:{}", body.videos[0]);
+
+    sqlite::upsert_db_video_groups(&body.videos, Some(&group_id))?;
+
+    tracing::info!("linked videos {:?}", body.videos);
+    pages::invalidate(pages::Change::Videos);
+
+    Ok(group_id)
+}
+
+// DELETE /admin/groups/{video_id}
+async fn delete_group(Path(video_id): Path<String>) -> Result<StatusCode, ApiError> {
+    sqlite::upsert_db_video_groups(std::slice::from_ref(&video_id), None)?;
+
+    tracing::info!("unlinked video {}", video_id);
+    pages::invalidate(pages::Change::Videos);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct ChannelRequest {
+    /// YouTube id, handle or url, or Twitch login or url
+    channel: String,
+    /// Only scan the channel for collabs with the tracked ones
+    #[serde(default)]
+    watched: bool,
+}
+
+/**
+ * Resolves a channel given to the admin API, rejecting inputs that aren't a channel reference
+ */
+async fn resolve_channel(input: &str) -> Result<(String, Platforms), ApiError> {
+    if channels::parse_channel_ref(input).is_none() && twitch::parse_channel_login(input).is_none()
+    {
+        return Err(ApiError::BadRequest(format!(
+            "invalid channel: {input}, expected a channel id, @handle or channel url"
+        )));
+    }
+
+    let channel = oshi::resolve_channel(input).await.map_err(ApiError::bad_request)?;
+
+    Ok((channel.id, channel.platform))
+}
+
+// POST /admin/channels
+async fn post_channels(Json(body): Json<ChannelRequest>) -> Result<String, ApiError> {
+    let (channel_id, platform) = resolve_channel(&body.channel).await?;
+    let channel = platform.get_channel(&channel_id).await.map_err(ApiError::bad_request)?;
+
+    tracing::info!("adding {} ({}) to db", channel.name, channel.id);
+    sqlite::upsert_db_channel(DbChannel { watched: i32::from(body.watched), ..channel })?;
+    pages::invalidate(pages::Change::Channels);
+
+    Ok(channel_id)
+}
+
+// DELETE /admin/channels/{channel}
+async fn delete_channel(Path(channel): Path<String>) -> Result<StatusCode, ApiError> {
+    let (channel_id, _) = resolve_channel(&channel).await?;
+
+    if !sqlite::disable_db_channel(&channel_id)? {
+        return Err(ApiError::NotFound(format!("channel {channel} is not tracked")));
+    }
+
+    tracing::info!("disabled channel {}", channel_id);
+    pages::invalidate(pages::Change::Channels);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::api::DbVideo;
+
+    async fn delete_status(token: Option<&str>, authorization: Option<&str>) -> StatusCode {
+        let mut req = Request::delete("/groups/dQw4w9WgXcQ");
+        if let Some(authorization) = authorization {
+            req = req.header(AUTHORIZATION, authorization);
+        }
+
+        let router = router(token.map(str::to_string));
+        router.oneshot(req.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_tokens() {
+        let token = Some("hunter2");
+
+        assert_eq!(delete_status(token, None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(delete_status(token, Some("Bearer hunter")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(delete_status(token, Some("Bearer hunter22")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(delete_status(token, Some("hunter2")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(delete_status(token, Some("Bearer ")).await, StatusCode::UNAUTHORIZED);
+        // hidden without a token, whatever is sent
+        assert_eq!(delete_status(None, Some("Bearer hunter2")).await, StatusCode::NOT_FOUND);
+    }
+
+    async fn send(method: &str, uri: &str, body: Option<&str>) -> (StatusCode, String) {
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(AUTHORIZATION, "Bearer hunter2")
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+
+        let res = router(Some("hunter2".to_string())).oneshot(req).await.unwrap();
+        let status = res.status();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn links_and_unlinks_videos() {
+        sqlite::init_db();
+
+        let channel_id = "UCadmingroups00000000000";
+        sqlite::upsert_db_channel(DbChannel {
+            id: channel_id.to_string(),
+            name: "Groups".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        sqlite::upsert_db_videos(
+            ["group0", "group1", "group2"]
+                .into_iter()
+                .map(|id| DbVideo {
+                    id: id.to_string(),
+                    channel_id: channel_id.to_string(),
+                    scheduled_time: "2026-01-01T12:00:00Z".to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        )
+        .unwrap();
+        let group_of = |id: &str| sqlite::get_db_video_groups().unwrap().get(id).cloned();
+
+        let (status, _) = send("POST", "/groups", Some(r#"{"videos":["group0","typo"]}"#)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(group_of("group0"), None);
+
+        let (status, body) =
+            send("POST", "/groups", Some(r#"{"videos":["group0","group1"]}"#)).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "manual:group0"));
+
+        // relinking leaves no overlapping group behind
+        let (status, body) =
+            send("POST", "/groups", Some(r#"{"videos":["group2","group1"]}"#)).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "manual:group2"));
+        assert_eq!(group_of("group0"), None);
+        assert_eq!(group_of("group1"), Some(Some("manual:group2".to_string())));
+
+        let (status, _) = send("DELETE", "/groups/group1", None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(group_of("group1"), Some(None));
+        assert_eq!(group_of("group2"), Some(Some("manual:group2".to_string())));
+    }
+}
@@ -1,18 +1,157 @@
+use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+use crate::platform::Platforms;
+
+pub mod v1;
+
+#[derive(Debug, Clone, Copy, Default, Hash, Eq, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum VideoKind {
+    #[default]
+    Live,
+    Premiere,
+    MembersOnly,
+    Placeholder,
+}
+
+impl VideoKind {
+    pub const ALL: [VideoKind; 4] =
+        [VideoKind::Live, VideoKind::Premiere, VideoKind::MembersOnly, VideoKind::Placeholder];
+
+    /// Kinds listed when no `?kind=` filter is given
+    pub const VISIBLE: [VideoKind; 3] =
+        [VideoKind::Live, VideoKind::Premiere, VideoKind::MembersOnly];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VideoKind::Live => "live",
+            VideoKind::Premiere => "premiere",
+            VideoKind::MembersOnly => "members-only",
+            VideoKind::Placeholder => "placeholder",
+        }
+    }
+}
+
+impl fmt::Display for VideoKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for VideoKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VideoKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| anyhow!("unknown video kind: {s}"))
+    }
+}
+
+/// Position in a listing, which continues after the video with this scheduled time and id
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct Cursor {
+    pub scheduled_time: String,
+    pub id: String,
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // opaque to clients, so its format can change without breaking links
+        f.write_str(&hex::encode(format!("{}\n{}", self.scheduled_time, self.id)))
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let decoded = hex::decode(s)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| anyhow!("invalid cursor: {s}"))?;
+
+        match decoded.split_once('\n') {
+            Some((scheduled_time, id)) if !id.is_empty() => {
+                Ok(Cursor { scheduled_time: scheduled_time.to_string(), id: id.to_string() })
+            }
+            _ => Err(anyhow!("invalid cursor: {s}")),
+        }
+    }
+}
+
+/// Whether a video has started, ended videos are never listed
+#[derive(Debug, Clone, Copy, Default, Hash, Eq, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum VideoStatus {
+    Live,
+    #[default]
+    Upcoming,
+}
+
+impl VideoStatus {
+    pub const ALL: [VideoStatus; 2] = [VideoStatus::Live, VideoStatus::Upcoming];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VideoStatus::Live => "live",
+            VideoStatus::Upcoming => "upcoming",
+        }
+    }
+}
+
+impl fmt::Display for VideoStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for VideoStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VideoStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == s)
+            .ok_or_else(|| anyhow!("unknown video status: {s}"))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct DbChannel {
     pub id: String,
     pub name: String,
+    pub platform: Platforms,
+    pub handle: Option<String>,
+    pub description: Option<String>,
+    pub country: Option<String>,
+    pub thumbnails: BTreeMap<String, String>,
+    pub banner: Option<String>,
+    pub subscriber_count: Option<i64>,
     pub disabled: i32,
+    pub watched: i32,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct DbVideo {
     pub id: String,
     pub channel_id: String,
     pub channel_name: Option<String>,
+    pub channel_handle: Option<String>,
+    pub platform: Platforms,
     pub title: String,
+    pub description: Option<String>,
+    pub thumbnails: BTreeMap<String, String>,
+    pub live_broadcast_content: String,
+    pub concurrent_viewers: Option<i64>,
+    pub duration: Option<i64>,
+    pub premiere: bool,
+    pub kind: VideoKind,
+    pub collab: bool,
     pub scheduled_time: String,
     pub start_time: Option<String>,
     pub end_time: Option<String>,
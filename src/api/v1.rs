@@ -0,0 +1,447 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, Request, rejection::QueryRejection},
+    http::{HeaderMap, HeaderValue, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, OpenApi, ToSchema, openapi};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    api::{DbChannel, DbVideo, VideoKind},
+    error::{ApiError, Problem},
+    events::Event,
+    oshi,
+    pages::PageContext,
+    platform::Platforms,
+    query::{self, RootQuery},
+    sqlite, time,
+};
+
+/// Where the API is mounted, used for the spec and pagination links
+pub const BASE_PATH: &str = "/api/v1";
+
+/// Headers responses depend on, the time zone and language being taken from them by default
+const VARY_VALUE: HeaderValue = HeaderValue::from_static("Accept, Accept-Language, TZ");
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Oshi API", version = "1.0.0", description = "Upcoming streams of my oshi"),
+    servers((url = "/api/v1")),
+    tags(
+        (name = "videos", description = "Upcoming and live streams"),
+        (name = "channels", description = "Tracked channels"),
+    )
+)]
+struct ApiDoc;
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Upcoming,
+    Live,
+    Ended,
+}
+
+/// A channel a stream can be watched on
+#[derive(Serialize, ToSchema)]
+struct WatchUrl {
+    id: String,
+    platform: Platforms,
+    channel_id: String,
+    url: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct VideoChannel {
+    id: String,
+    name: Option<String>,
+    handle: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Video {
+    id: String,
+    title: String,
+    url: String,
+    /// Every channel the stream is simulcast on, starting with `url`
+    urls: Vec<WatchUrl>,
+    platform: Platforms,
+    status: Status,
+    kind: VideoKind,
+    /// Whether the stream is hosted by a channel other than the requested ones
+    collab: bool,
+    channel: VideoChannel,
+    description: Option<String>,
+    thumbnails: BTreeMap<String, String>,
+    concurrent_viewers: Option<i64>,
+    /// Length in seconds, for premieres
+    duration: Option<i64>,
+    premiere: bool,
+    /// RFC3339
+    scheduled_time: String,
+    start_time: Option<String>,
+    end_time: Option<String>,
+    /// `scheduled_time` in the zone given by `tz`
+    scheduled_time_local: String,
+    start_time_local: Option<String>,
+    end_time_local: Option<String>,
+    /// Until `scheduled_time` when the response was made, negative once it has passed
+    seconds_until_start: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct VideosResponse {
+    videos: Vec<Video>,
+    /// Link to the following videos, if there are more
+    next: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Channel {
+    id: String,
+    /// Name of the talent the channel belongs to
+    talent: String,
+    name: String,
+    url: String,
+    platform: Platforms,
+    handle: Option<String>,
+    description: Option<String>,
+    country: Option<String>,
+    thumbnails: BTreeMap<String, String>,
+    banner: Option<String>,
+    subscriber_count: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ChannelsResponse {
+    channels: Vec<Channel>,
+}
+
+/// Query parameters of `GET /videos/{id}`
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct VideoQuery {
+    /// IANA time zone dates are shown in, like `Asia/Tokyo`, UTC by default
+    tz: Option<String>,
+}
+
+pub fn router() -> Router {
+    let (router, spec) = routes();
+
+    router
+        .route("/openapi.json", get(|| async move { Json(spec) }))
+        .fallback(|| async { ApiError::NotFound("not found".to_string()) })
+        .layer(middleware::from_fn(vary))
+}
+
+async fn vary(req: Request, next: Next) -> Response {
+    let mut res = next.run(req).await;
+    res.headers_mut().insert(header::VARY, VARY_VALUE);
+    res
+}
+
+/**
+ * The documented routes along with their spec, built together so they can't drift apart
+ */
+fn routes() -> (Router, openapi::OpenApi) {
+    OpenApiRouter::with_openapi(ApiDoc::openapi())
+        .routes(routes!(get_videos))
+        .routes(routes!(get_video))
+        .routes(routes!(get_channels))
+        .routes(routes!(get_channel_videos))
+        .split_for_parts()
+}
+
+/// List upcoming and live videos
+#[utoipa::path(
+    get,
+    path = "/videos",
+    tag = "videos",
+    params(RootQuery),
+    responses(
+        (status = 200, body = VideosResponse),
+        (status = 400, description = "Invalid parameters", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown talent or group", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn get_videos(
+    query: Result<Query<RootQuery>, QueryRejection>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let Query(query) = query?;
+
+    list_videos(&PageContext::try_from(query.with_headers(&headers))?)
+}
+
+/// Get a single video
+#[utoipa::path(
+    get,
+    path = "/videos/{id}",
+    tag = "videos",
+    params(("id" = String, Path, description = "Video id"), VideoQuery),
+    responses(
+        (status = 200, body = Video),
+        (status = 400, description = "Invalid time zone", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown video", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn get_video(
+    Path(id): Path<String>,
+    query: Result<Query<VideoQuery>, QueryRejection>,
+    headers: HeaderMap,
+) -> Result<Json<Video>, ApiError> {
+    let Query(query) = query?;
+
+    // like the listings, the `TZ` header is used when `?tz=` isn't given
+    let tz = query
+        .tz
+        .as_deref()
+        .or_else(|| headers.get(query::TZ_HEADER).and_then(|val| val.to_str().ok()))
+        .map(|tz| time::parse_tz(tz.trim()))
+        .transpose()
+        .map_err(ApiError::bad_request)?
+        .unwrap_or(Tz::UTC);
+
+    match sqlite::get_db_video(&id)? {
+        Some(video) => Ok(Json(to_video(&Event { videos: vec![video] }, tz))),
+        None => Err(ApiError::NotFound(format!("video {id} not found"))),
+    }
+}
+
+/// List tracked channels
+#[utoipa::path(
+    get,
+    path = "/channels",
+    tag = "channels",
+    responses(
+        (status = 200, body = ChannelsResponse),
+        (status = 503, description = "Database unavailable", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn get_channels() -> Result<Json<ChannelsResponse>, ApiError> {
+    let channels = sqlite::get_db_channels()?;
+
+    let channels = oshi::get_talents()
+        .into_iter()
+        .flat_map(|talent| {
+            talent
+                .channels
+                .iter()
+                .filter_map(|oshi| channels.iter().find(|c| c.id == oshi.id))
+                .map(|channel| to_channel(&talent.name, channel))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    Ok(Json(ChannelsResponse { channels }))
+}
+
+/// List upcoming and live videos of a talent
+#[utoipa::path(
+    get,
+    path = "/channels/{alias}/videos",
+    tag = "channels",
+    params(("alias" = String, Path, description = "Name or alias of the talent"), RootQuery),
+    responses(
+        (status = 200, body = VideosResponse),
+        (status = 400, description = "Invalid parameters", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown talent", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn get_channel_videos(
+    Path(alias): Path<String>,
+    query: Result<Query<RootQuery>, QueryRejection>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let Some(talent) = oshi::get_talent(&alias) else {
+        return Err(ApiError::NotFound(format!("{alias} is not tracked")));
+    };
+
+    let Query(query) = query?;
+    let ctx = PageContext::try_from(query.with_headers(&headers))?;
+
+    list_videos(&PageContext { oshi: vec![talent.name], ..ctx })
+}
+
+fn list_videos(ctx: &PageContext) -> Result<Response, ApiError> {
+    let page = query::get_video_page(ctx)?;
+
+    let next =
+        page.next.as_ref().map(|next| query::page_link(&format!("{BASE_PATH}/videos"), next));
+
+    let mut headers = HeaderMap::new();
+    if let Some(next) = &next
+        && let Ok(link) = HeaderValue::from_str(&format!("<{next}>; rel=\"next\""))
+    {
+        headers.insert(header::LINK, link);
+    }
+
+    let videos = page.events.iter().map(|event| to_video(event, ctx.tz)).collect();
+
+    Ok((headers, Json(VideosResponse { videos, next })).into_response())
+}
+
+fn to_video(event: &Event, tz: Tz) -> Video {
+    let video: &DbVideo = event.primary();
+
+    let status = if video.end_time.is_some() {
+        Status::Ended
+    } else if video.start_time.is_some() {
+        Status::Live
+    } else {
+        Status::Upcoming
+    };
+
+    Video {
+        id: video.id.clone(),
+        title: video.title.clone(),
+        url: video.platform.video_url(video),
+        urls: event
+            .videos
+            .iter()
+            .map(|v| WatchUrl {
+                id: v.id.clone(),
+                platform: v.platform,
+                channel_id: v.channel_id.clone(),
+                url: v.platform.video_url(v),
+            })
+            .collect(),
+        platform: video.platform,
+        status,
+        kind: video.kind,
+        collab: video.collab,
+        channel: VideoChannel {
+            id: video.channel_id.clone(),
+            name: video.channel_name.clone(),
+            handle: video.channel_handle.clone(),
+        },
+        description: video.description.clone(),
+        thumbnails: video.thumbnails.clone(),
+        concurrent_viewers: video.concurrent_viewers,
+        duration: video.duration,
+        premiere: video.premiere,
+        scheduled_time: video.scheduled_time.clone(),
+        start_time: video.start_time.clone(),
+        end_time: video.end_time.clone(),
+        scheduled_time_local: time::localize(&video.scheduled_time, tz),
+        start_time_local: video.start_time.as_deref().map(|time| time::localize(time, tz)),
+        end_time_local: video.end_time.as_deref().map(|time| time::localize(time, tz)),
+        seconds_until_start: time::seconds_until(&video.scheduled_time),
+    }
+}
+
+fn to_channel(talent: &str, channel: &DbChannel) -> Channel {
+    Channel {
+        id: channel.id.clone(),
+        talent: talent.to_string(),
+        name: channel.name.clone(),
+        url: channel.platform.channel_url(channel),
+        platform: channel.platform,
+        handle: channel.handle.clone(),
+        description: channel.description.clone(),
+        country: channel.country.clone(),
+        thumbnails: channel.thumbnails.clone(),
+        banner: channel.banner.clone(),
+        subscriber_count: channel.subscriber_count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn spec_matches_routes() {
+        let (router, spec) = routes();
+
+        assert!(spec.openapi == openapi::OpenApiVersion::Version31);
+
+        // answers matched routes without running the handlers, which need the db
+        let router =
+            router.route_layer(axum::middleware::from_fn(|_req: Request, _next: Next| async {
+                StatusCode::NO_CONTENT
+            }));
+
+        let addr = testing::serve(router).await;
+
+        let client = reqwest::Client::new();
+        let mut operations = vec![];
+
+        for (path, item) in &spec.paths.paths {
+            let documented = [
+                ("GET", item.get.is_some()),
+                ("POST", item.post.is_some()),
+                ("PUT", item.put.is_some()),
+                ("PATCH", item.patch.is_some()),
+                ("DELETE", item.delete.is_some()),
+            ];
+
+            for (method, _) in documented.into_iter().filter(|(_, documented)| *documented) {
+                let url =
+                    format!("http://{addr}{}", path.replace("{id}", "x").replace("{alias}", "x"));
+                let res = client.request(method.parse().unwrap(), &url).send().await.unwrap();
+
+                assert_eq!(res.status(), StatusCode::NO_CONTENT, "{method} {path} isn't served");
+                operations.push(format!("{method} {path}"));
+            }
+        }
+
+        operations.sort();
+        assert_eq!(
+            operations,
+            ["GET /channels", "GET /channels/{alias}/videos", "GET /videos", "GET /videos/{id}"]
+        );
+    }
+
+    #[tokio::test]
+    async fn localizes_videos() {
+        sqlite::init_db();
+        sqlite::upsert_db_channel(DbChannel {
+            id: "UClocalizedvideos0000000".to_string(),
+            name: "Localized".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        sqlite::upsert_db_videos(vec![DbVideo {
+            id: "localized01".to_string(),
+            channel_id: "UClocalizedvideos0000000".to_string(),
+            title: "karaoke".to_string(),
+            scheduled_time: "2026-01-01T12:00:00Z".to_string(),
+            ..Default::default()
+        }])
+        .unwrap();
+
+        let addr = testing::serve(router()).await;
+        let client = reqwest::Client::new();
+        let url = format!("http://{addr}/videos/localized01");
+
+        let local_time = |res: reqwest::Response| async move {
+            assert_eq!(res.headers()[header::VARY], VARY_VALUE);
+            let body: serde_json::Value = res.json().await.unwrap();
+            body["scheduled_time_local"].as_str().unwrap().to_string()
+        };
+
+        let res = client.get(&url).send().await.unwrap();
+        assert_eq!(local_time(res).await, "2026-01-01T12:00:00Z");
+
+        let res = client.get(format!("{url}?tz=Asia/Tokyo")).send().await.unwrap();
+        assert_eq!(local_time(res).await, "2026-01-01T21:00:00+09:00");
+
+        let res =
+            client.get(&url).header(query::TZ_HEADER, "America/New_York").send().await.unwrap();
+        assert_eq!(local_time(res).await, "2026-01-01T07:00:00-05:00");
+
+        let res = client.get(format!("{url}?tz=Mars/Olympus_Mons")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.headers()[header::VARY], VARY_VALUE);
+    }
+}
@@ -1,33 +1,139 @@
-pub trait Colorize {
-    fn light_blue(&self) -> String;
+use anyhow::{Result, anyhow};
+use std::{fmt, str::FromStr};
+
+/// What a piece of text is, the theme picking its colour
+#[derive(Clone, Copy, Debug)]
+pub enum Role {
+    Link,
+    Title,
+    Live,
+    Upcoming,
+    Ended,
+    Error,
+    /// A passing health check
+    Healthy,
+}
+
+/// A colour in each palette, the closest match where a palette lacks it
+#[derive(Clone, Copy, Debug)]
+struct Color {
+    /// SGR foreground code, 30 to 37 or 90 to 97
+    ansi16: u8,
+    ansi256: u8,
+    rgb: (u8, u8, u8),
+}
 
-    fn green(&self) -> String;
+struct Theme {
+    link: Color,
+    title: Color,
+    live: Color,
+    upcoming: Color,
+    ended: Color,
+    error: Color,
+    healthy: Color,
+}
 
-    fn bright_red(&self) -> String;
+impl Theme {
+    fn color(&self, role: Role) -> Color {
+        match role {
+            Role::Link => self.link,
+            Role::Title => self.title,
+            Role::Live => self.live,
+            Role::Upcoming => self.upcoming,
+            Role::Ended => self.ended,
+            Role::Error => self.error,
+            Role::Healthy => self.healthy,
+        }
+    }
+}
 
-    fn bright_yellow(&self) -> String;
+static THEME: Theme = Theme {
+    link: Color { ansi16: 94, ansi256: 117, rgb: (135, 215, 255) },
+    title: Color { ansi16: 92, ansi256: 120, rgb: (135, 255, 135) },
+    live: Color { ansi16: 91, ansi256: 196, rgb: (255, 0, 0) },
+    upcoming: Color { ansi16: 93, ansi256: 226, rgb: (255, 255, 0) },
+    ended: Color { ansi16: 35, ansi256: 129, rgb: (175, 0, 255) },
+    error: Color { ansi16: 91, ansi256: 196, rgb: (255, 0, 0) },
+    healthy: Color { ansi16: 32, ansi256: 34, rgb: (0, 175, 0) },
+};
 
-    fn bright_purple(&self) -> String;
+/// Palette text pages are coloured with
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub enum ColorMode {
+    Never,
+    Ansi16,
+    #[default]
+    Ansi256,
+    TrueColor,
 }
 
-impl Colorize for str {
-    fn light_blue(&self) -> String {
-        format!("\x1b[38;5;117m{self}\x1b[0m")
+impl ColorMode {
+    /**
+     * SGR parameters of a colour in the palette, none when colours are off
+     */
+    fn sgr(&self, color: Color) -> Option<String> {
+        match self {
+            ColorMode::Never => None,
+            ColorMode::Ansi16 => Some(color.ansi16.to_string()),
+            ColorMode::Ansi256 => Some(format!("38;5;{}", color.ansi256)),
+            ColorMode::TrueColor => {
+                let (r, g, b) = color.rgb;
+                Some(format!("38;2;{r};{g};{b}"))
+            }
+        }
     }
+}
 
-    fn green(&self) -> String {
-        format!("\x1b[38;5;120m{self}\x1b[0m")
+impl fmt::Display for ColorMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorMode::Never => write!(f, "never"),
+            ColorMode::Ansi16 => write!(f, "16"),
+            ColorMode::Ansi256 => write!(f, "256"),
+            ColorMode::TrueColor => write!(f, "truecolor"),
+        }
     }
+}
+
+impl FromStr for ColorMode {
+    type Err = anyhow::Error;
 
-    fn bright_red(&self) -> String {
-        format!("\x1b[38;5;196m{self}\x1b[0m")
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "never" => Ok(ColorMode::Never),
+            "16" => Ok(ColorMode::Ansi16),
+            "always" | "256" => Ok(ColorMode::Ansi256),
+            "truecolor" => Ok(ColorMode::TrueColor),
+            _ => Err(anyhow!("invalid color: {s}, expected never, always, 16, 256 or truecolor")),
+        }
     }
+}
+
+pub trait Colorize {
+    fn paint(&self, role: Role, mode: ColorMode) -> String;
+}
 
-    fn bright_yellow(&self) -> String {
-        format!("\x1b[38;5;226m{self}\x1b[0m")
+impl Colorize for str {
+    fn paint(&self, role: Role, mode: ColorMode) -> String {
+        match mode.sgr(THEME.color(role)) {
+            Some(sgr) => format!("\x1b[{sgr}m{self}\x1b[0m"),
+            None => self.to_string(),
+        }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paints_in_each_palette() {
+        assert_eq!("live".paint(Role::Live, ColorMode::Never), "live");
+        assert_eq!("live".paint(Role::Live, ColorMode::Ansi16), "\x1b[91mlive\x1b[0m");
+        assert_eq!("live".paint(Role::Live, ColorMode::Ansi256), "\x1b[38;5;196mlive\x1b[0m");
+        assert_eq!("live".paint(Role::Live, ColorMode::TrueColor), "\x1b[38;2;255;0;0mlive\x1b[0m");
 
-    fn bright_purple(&self) -> String {
-        format!("\x1b[38;5;129m{self}\x1b[0m")
+        assert_eq!("always".parse::<ColorMode>().unwrap(), ColorMode::Ansi256);
+        assert!("rainbow".parse::<ColorMode>().is_err());
     }
 }
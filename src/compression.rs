@@ -0,0 +1,64 @@
+use tower_http::compression::{
+    CompressionLayer, DefaultPredicate, Predicate,
+    predicate::{And, SizeAbove},
+};
+
+pub type CompressionPredicate = And<DefaultPredicate, SizeAbove>;
+
+/**
+ * Compresses responses larger than `min_size` bytes with gzip, brotli or zstd, as accepted by the client
+ */
+pub fn layer(min_size: u16) -> CompressionLayer<CompressionPredicate> {
+    CompressionLayer::new()
+        .gzip(true)
+        .br(true)
+        .zstd(true)
+        .compress_when(DefaultPredicate::new().and(SizeAbove::new(min_size)))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, http::header, routing::get};
+
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn compresses_large_responses() {
+        let router = Router::new()
+            .route("/small", get(|| async { "a".repeat(100) }))
+            .route("/large", get(|| async { "a".repeat(2000) }))
+            .layer(layer(1024));
+
+        let addr = testing::serve(router).await;
+
+        let client = reqwest::Client::new();
+        let encoding = |path: &'static str, method: reqwest::Method, accept: &'static str| {
+            let req = client
+                .request(method, format!("http://{addr}{path}"))
+                .header(header::ACCEPT_ENCODING, accept);
+
+            async move {
+                let res = req.send().await.unwrap();
+                assert!(res.status().is_success());
+                res.headers()
+                    .get(header::CONTENT_ENCODING)
+                    .map(|val| val.to_str().unwrap().to_string())
+            }
+        };
+
+        for accept in ["gzip", "br", "zstd"] {
+            assert_eq!(
+                encoding("/large", reqwest::Method::GET, accept).await.as_deref(),
+                Some(accept)
+            );
+            assert_eq!(
+                encoding("/large", reqwest::Method::HEAD, accept).await.as_deref(),
+                Some(accept)
+            );
+            assert_eq!(encoding("/small", reqwest::Method::GET, accept).await, None);
+        }
+
+        assert_eq!(encoding("/large", reqwest::Method::GET, "identity").await, None);
+    }
+}
@@ -0,0 +1,103 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{
+        HeaderMap, HeaderName, Method, StatusCode,
+        header::{
+            CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+        },
+    },
+    middleware::Next,
+    response::Response,
+};
+
+/**
+ * Answers `304 Not Modified` when the client already has the page (RFC 9110 §13)
+ */
+pub async fn not_modified(req: Request, next: Next) -> Response {
+    let conditional = matches!(*req.method(), Method::GET | Method::HEAD);
+    let req_headers = req.headers().clone();
+    let res = next.run(req).await;
+
+    if !conditional || res.status() != StatusCode::OK || !is_fresh(&req_headers, res.headers()) {
+        return res;
+    }
+
+    let (mut parts, _) = res.into_parts();
+    parts.status = StatusCode::NOT_MODIFIED;
+    parts.headers.remove(CONTENT_TYPE);
+    parts.headers.remove(CONTENT_LENGTH);
+
+    Response::from_parts(parts, Body::empty())
+}
+
+/**
+ * Whether the validators sent by the client match the response,
+ * `If-Modified-Since` being ignored when `If-None-Match` is given
+ */
+fn is_fresh(req_headers: &HeaderMap, res_headers: &HeaderMap) -> bool {
+    if let Some(tags) = header(req_headers, IF_NONE_MATCH) {
+        let Some(etag) = header(res_headers, ETAG) else {
+            return false;
+        };
+
+        // weak comparison, as GET requests allow
+        let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+        return tags.split(',').any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag));
+    }
+
+    let since =
+        header(req_headers, IF_MODIFIED_SINCE).and_then(|val| httpdate::parse_http_date(val).ok());
+    let modified =
+        header(res_headers, LAST_MODIFIED).and_then(|val| httpdate::parse_http_date(val).ok());
+
+    match (since, modified) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+fn header(headers: &HeaderMap, name: HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|val| val.to_str().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs.iter().map(|(name, val)| (name.parse().unwrap(), val.parse().unwrap())).collect()
+    }
+
+    #[test]
+    fn matches_entity_tags() {
+        let res =
+            headers(&[("etag", "\"abc\""), ("last-modified", "Thu, 01 Jan 2026 12:00:00 GMT")]);
+
+        assert!(is_fresh(&headers(&[("if-none-match", "\"abc\"")]), &res));
+        assert!(is_fresh(&headers(&[("if-none-match", "\"xyz\", W/\"abc\"")]), &res));
+        assert!(is_fresh(&headers(&[("if-none-match", "*")]), &res));
+        assert!(!is_fresh(&headers(&[("if-none-match", "\"xyz\"")]), &res));
+        assert!(!is_fresh(&headers(&[]), &res));
+
+        // an entity tag takes precedence over the date
+        assert!(!is_fresh(
+            &headers(&[
+                ("if-none-match", "\"xyz\""),
+                ("if-modified-since", "Thu, 01 Jan 2026 12:00:00 GMT")
+            ]),
+            &res
+        ));
+    }
+
+    #[test]
+    fn compares_modification_dates() {
+        let res = headers(&[("last-modified", "Thu, 01 Jan 2026 12:00:00 GMT")]);
+        let since = |date| headers(&[("if-modified-since", date)]);
+
+        assert!(is_fresh(&since("Thu, 01 Jan 2026 12:00:00 GMT"), &res));
+        assert!(is_fresh(&since("Fri, 02 Jan 2026 12:00:00 GMT"), &res));
+        assert!(!is_fresh(&since("Thu, 01 Jan 2026 11:59:59 GMT"), &res));
+        assert!(!is_fresh(&since("yesterday"), &res));
+    }
+}
@@ -1,47 +1,278 @@
-use std::{collections::HashMap, env, sync::LazyLock};
+use std::{collections::BTreeMap, env, sync::LazyLock};
 
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub log_level: tracing::Level,
+    /// Patterns of the user agents answered with text, matched against each product name
+    pub terminal_agents: Vec<String>,
+    /// Smallest response body that gets compressed
+    pub compression_min_bytes: u16,
 }
 
 pub struct YoutubeConfig {
     pub apikey: String,
 }
 
+pub struct TwitchConfig {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub api_url: String,
+    pub auth_url: String,
+}
+
+pub struct WebsubConfig {
+    pub hub_url: String,
+    pub callback_url: Option<String>,
+    pub secret: Option<String>,
+    pub lease_seconds: u64,
+}
+
+impl WebsubConfig {
+    /**
+     * Notifications are only accepted when the hub signs them with the secret
+     */
+    pub fn enabled(&self) -> bool {
+        self.callback_url.is_some() && self.secret.is_some()
+    }
+}
+
+pub struct ClassifyConfig {
+    pub placeholder_keywords: Vec<String>,
+    pub members_keywords: Vec<String>,
+    /// How far ahead a frame has to be scheduled to be a placeholder
+    pub placeholder_days: i64,
+    /// Premieres up to this many seconds long are frames rather than videos
+    pub placeholder_max_duration: i64,
+}
+
+pub struct SimulcastConfig {
+    pub tolerance_minutes: i64,
+    pub title_similarity: Option<f64>,
+}
+
+pub struct CacheConfig {
+    /// How long rendered pages are kept, relative times in them go stale otherwise
+    pub ttl_seconds: u64,
+    /// Most pages kept at once, every filter and cursor being cached apart
+    pub max_entries: usize,
+}
+
+pub struct HealthConfig {
+    /// Scheduled runs a job can miss before readiness fails
+    pub stale_runs: i32,
+}
+
+pub struct AdminConfig {
+    pub token: Option<String>,
+}
+
+pub struct TalentConfig {
+    pub name: String,
+    /// Nicknames accepted by `?oshi=`, besides the name
+    pub aliases: Vec<String>,
+    /// Channel ids, handles or urls of the main and sub channels
+    pub channels: Vec<String>,
+}
+
+impl TalentConfig {
+    fn new(name: &str, aliases: &[&str], channels: &[&str]) -> Self {
+        TalentConfig {
+            name: name.to_string(),
+            aliases: aliases.iter().map(|s| s.to_string()).collect(),
+            channels: channels.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
 pub struct Config {
     pub server: ServerConfig,
     pub youtube: YoutubeConfig,
-    pub browser_redirect: String,
-    pub oshi: HashMap<String, String>,
+    pub twitch: TwitchConfig,
+    pub websub: WebsubConfig,
+    pub classify: ClassifyConfig,
+    pub watched: Vec<String>,
+    pub simulcast: SimulcastConfig,
+    pub admin: AdminConfig,
+    pub cache: CacheConfig,
+    pub health: HealthConfig,
+    pub browser_redirect: &'static str,
+    pub talents: Vec<TalentConfig>,
+    /// Units or agencies, mapped to the names of their members
+    pub groups: BTreeMap<String, Vec<String>>,
 }
 
 impl Config {
+    #[cfg(not(test))]
     fn get() -> Config {
+        Config::with_apikey(env::var("YOUTUBE_APIKEY").unwrap())
+    }
+
+    /**
+     * The config tests run with, which never reach the API and so need no key
+     */
+    #[cfg(test)]
+    fn for_tests() -> Config {
+        Config::with_apikey(String::new())
+    }
+
+    fn with_apikey(apikey: String) -> Config {
         let level = match env::var("DEBUG_LOG").as_deref() {
             Ok("1") | Ok("true") => tracing::Level::DEBUG,
             _ => tracing::Level::INFO,
         };
 
         Config {
-            browser_redirect: "https://github.com/killbasa/oshi-api".into(),
+            browser_redirect: "https://github.com/killbasa/oshi-api",
             server: ServerConfig {
                 host: env::var("HOST").unwrap_or("127.0.0.1".to_string()),
                 port: env::var("PORT").unwrap_or("3000".to_string()).parse().unwrap_or(3000),
                 log_level: level,
+                terminal_agents: get_list(
+                    "TERMINAL_USER_AGENTS",
+                    &["curl", "wget", "httpie", "powershell", "xh", "aria2"],
+                ),
+                compression_min_bytes: env::var("COMPRESSION_MIN_BYTES")
+                    .unwrap_or("1024".to_string())
+                    .parse()
+                    .unwrap_or(1024),
+            },
+            youtube: YoutubeConfig { apikey },
+            twitch: TwitchConfig {
+                client_id: env::var("TWITCH_CLIENT_ID").ok(),
+                client_secret: env::var("TWITCH_CLIENT_SECRET").ok(),
+                api_url: env::var("TWITCH_API_URL")
+                    .unwrap_or("https://api.twitch.tv/helix".to_string()),
+                auth_url: env::var("TWITCH_AUTH_URL")
+                    .unwrap_or("https://id.twitch.tv/oauth2/token".to_string()),
+            },
+            websub: WebsubConfig {
+                hub_url: env::var("WEBSUB_HUB_URL")
+                    .unwrap_or("https://pubsubhubbub.appspot.com/subscribe".to_string()),
+                callback_url: env::var("WEBSUB_CALLBACK_URL").ok(),
+                secret: env::var("WEBSUB_SECRET").ok(),
+                lease_seconds: env::var("WEBSUB_LEASE_SECONDS")
+                    .unwrap_or("432000".to_string())
+                    .parse()
+                    .unwrap_or(432000),
+            },
+            classify: ClassifyConfig {
+                placeholder_keywords: get_list(
+                    "PLACEHOLDER_KEYWORDS",
+                    &["free chat", "freechat", "フリーチャット", "フリチャ"],
+                ),
+                members_keywords: get_list(
+                    "MEMBERS_KEYWORDS",
+                    &["members only", "member only", "members-only", "メン限", "メンバー限定"],
+                ),
+                placeholder_days: env::var("PLACEHOLDER_DAYS")
+                    .unwrap_or("30".to_string())
+                    .parse()
+                    .unwrap_or(30),
+                placeholder_max_duration: env::var("PLACEHOLDER_MAX_DURATION")
+                    .unwrap_or("60".to_string())
+                    .parse()
+                    .unwrap_or(60),
+            },
+            watched: get_list("WATCHED_CHANNELS", &[]),
+            simulcast: SimulcastConfig {
+                tolerance_minutes: env::var("SIMULCAST_TOLERANCE_MINUTES")
+                    .unwrap_or("15".to_string())
+                    .parse()
+                    .unwrap_or(15),
+                title_similarity: env::var("SIMULCAST_TITLE_SIMILARITY")
+                    .ok()
+                    .and_then(|val| val.parse().ok()),
+            },
+            admin: AdminConfig {
+                token: env::var("ADMIN_TOKEN").ok().filter(|token| !token.is_empty()),
+            },
+            cache: CacheConfig {
+                ttl_seconds: env::var("PAGE_CACHE_TTL_SECONDS")
+                    .unwrap_or("60".to_string())
+                    .parse()
+                    .unwrap_or(60),
+                max_entries: env::var("PAGE_CACHE_MAX_ENTRIES")
+                    .unwrap_or("1000".to_string())
+                    .parse()
+                    .unwrap_or(1000),
             },
-            youtube: YoutubeConfig {
-                apikey: env::var("YOUTUBE_APIKEY").unwrap(), //
+            health: HealthConfig {
+                stale_runs: env::var("HEALTH_STALE_RUNS")
+                    .unwrap_or("3".to_string())
+                    .parse()
+                    .unwrap_or(3),
             },
-            oshi: HashMap::from([
-                ("furi".into(), "UCb8dLvDvmZ-d92KEy_9oWog".into()),
-                ("phish".into(), "UC9iiZCKQ9jnIM7zZ_mRX_cg".into()),
-                ("mono".into(), "UCdubotSy4pPOsiaW4MrYn3Q".into()),
-                ("raki".into(), "UCtuoyOZhnxJ12pE294FdH8Q".into()),
-            ]),
+            talents: get_talents(
+                "TALENTS",
+                vec![
+                    TalentConfig::new("furi", &[], &["UCb8dLvDvmZ-d92KEy_9oWog"]),
+                    TalentConfig::new("phish", &[], &["UC9iiZCKQ9jnIM7zZ_mRX_cg"]),
+                    TalentConfig::new("mono", &[], &["UCdubotSy4pPOsiaW4MrYn3Q"]),
+                    TalentConfig::new("raki", &[], &["UCtuoyOZhnxJ12pE294FdH8Q"]),
+                ],
+            ),
+            groups: get_groups("OSHI_GROUPS"),
         }
     }
 }
 
+/**
+ * Reads a comma-separated env var, falling back to `default` when unset
+ */
+fn get_list(key: &str, default: &[&str]) -> Vec<String> {
+    match env::var(key) {
+        Ok(val) => val.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect(),
+        Err(_) => default.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+/**
+ * Reads talents formatted as `name|alias,alias|channel,channel;...`, falling back to `default` when unset
+ */
+fn get_talents(key: &str, default: Vec<TalentConfig>) -> Vec<TalentConfig> {
+    let Ok(val) = env::var(key) else {
+        return default;
+    };
+
+    val.split(';')
+        .filter_map(|talent| {
+            let mut parts = talent.split('|');
+            let name = parts.next()?.trim();
+            let aliases: Vec<&str> =
+                parts.next()?.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
+            let channels: Vec<&str> =
+                parts.next()?.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
+
+            (!name.is_empty() && !channels.is_empty())
+                .then(|| TalentConfig::new(name, &aliases, &channels))
+        })
+        .collect()
+}
+
+/**
+ * Reads groups formatted as `name=alias,alias;name=alias,alias`
+ */
+fn get_groups(key: &str) -> BTreeMap<String, Vec<String>> {
+    env::var(key)
+        .unwrap_or_default()
+        .split(';')
+        .filter_map(|group| {
+            let (name, members) = group.split_once('=')?;
+            let members: Vec<String> = members
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+
+            (!name.trim().is_empty() && !members.is_empty())
+                .then(|| (name.trim().to_string(), members))
+        })
+        .collect()
+}
+
+#[cfg(not(test))]
 pub static CONFIG: LazyLock<Config> = LazyLock::new(Config::get);
+
+#[cfg(test)]
+pub static CONFIG: LazyLock<Config> = LazyLock::new(Config::for_tests);
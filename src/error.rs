@@ -0,0 +1,210 @@
+use axum::{
+    Json,
+    extract::{Request, rejection::QueryRejection},
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::fmt;
+use utoipa::ToSchema;
+
+use crate::{
+    colors::{ColorMode, Colorize, Role},
+    negotiate::{ResponseFormat, negotiate},
+    text,
+};
+
+const PROBLEM_HEADER: HeaderValue = HeaderValue::from_static("application/problem+json");
+const TEXT_HEADER: HeaderValue = HeaderValue::from_static("text/plain");
+
+/// A failed request, answered with the matching status code
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApiError {
+    /// Unknown talent, group or video
+    NotFound(String),
+    /// Invalid query parameters
+    BadRequest(String),
+    /// The page can't be shown in the negotiated format
+    NotAcceptable(String),
+    /// The database couldn't be read
+    Unavailable,
+}
+
+/// RFC 9457 problem details
+#[derive(Serialize, ToSchema)]
+pub struct Problem {
+    /// Always `about:blank`, the status code says it all
+    #[serde(rename = "type")]
+    kind: String,
+    title: String,
+    status: u16,
+    detail: String,
+}
+
+impl ApiError {
+    /**
+     * Wraps a parsing error of a query parameter
+     */
+    pub fn bad_request(e: impl fmt::Display) -> Self {
+        ApiError::BadRequest(e.to_string())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::NotAcceptable(_) => StatusCode::NOT_ACCEPTABLE,
+            ApiError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    pub fn problem(&self) -> Problem {
+        let status = self.status();
+
+        Problem {
+            kind: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: self.to_string(),
+        }
+    }
+
+    /**
+     * The error as a coloured message for terminals
+     */
+    pub fn into_text_response(self, color: ColorMode) -> Response {
+        let body = format!("{} {}", "error:".paint(Role::Error, color), self);
+
+        (self.status(), [(header::CONTENT_TYPE, TEXT_HEADER)], body).into_response()
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::NotFound(message)
+            | ApiError::BadRequest(message)
+            | ApiError::NotAcceptable(message) => f.write_str(message),
+            ApiError::Unavailable => f.write_str("the database is unavailable, try again later"),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<anyhow::Error> for ApiError {
+    /**
+     * Keeps errors raised as an `ApiError`, anything else comes from the database
+     */
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<ApiError>() {
+            Ok(e) => e,
+            Err(e) => {
+                tracing::error!("failed to read the database: {e}");
+                ApiError::Unavailable
+            }
+        }
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::BadRequest(rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    /**
+     * Problem details, which [`render_errors`] swaps for text on negotiated routes
+     */
+    fn into_response(self) -> Response {
+        let mut res =
+            (self.status(), [(header::CONTENT_TYPE, PROBLEM_HEADER)], Json(self.problem()))
+                .into_response();
+
+        res.extensions_mut().insert(self);
+        res
+    }
+}
+
+/**
+ * Answers errors as text to clients that negotiated it
+ */
+pub async fn render_errors(req: Request, next: Next) -> Response {
+    let format = negotiate(req.uri(), req.headers());
+    // an invalid `?color=` is itself answered with an error, in the default colours
+    let color = text::negotiate(req.uri().query(), req.headers())
+        .map(|options| options.color)
+        .unwrap_or_default();
+    let mut res = next.run(req).await;
+
+    match (format, res.extensions_mut().remove::<ApiError>()) {
+        (Ok(ResponseFormat::Text), Some(e)) => e.into_text_response(color),
+        _ => res,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, middleware, routing::get};
+
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn renders_negotiated_format() {
+        let router = Router::new()
+            .route(
+                "/",
+                get(|| async { Err::<(), _>(ApiError::NotFound("raki is not tracked".into())) }),
+            )
+            .layer(middleware::from_fn(render_errors));
+
+        let addr = testing::serve(router).await;
+
+        let client = reqwest::Client::new();
+        let url = format!("http://{addr}/");
+
+        let res = client.get(&url).header("accept", "application/json").send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "application/problem+json");
+
+        let body: serde_json::Value = res.json().await.unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Not Found",
+                "status": 404,
+                "detail": "raki is not tracked",
+            })
+        );
+
+        let res = client.get(&url).header("accept", "text/plain").send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/plain");
+        assert_eq!(
+            res.text().await.unwrap(),
+            format!("{} raki is not tracked", "error:".paint(Role::Error, ColorMode::Ansi256))
+        );
+
+        let res = client
+            .get(&url)
+            .header("accept", "text/plain")
+            .header(text::NO_COLOR_HEADER, "1")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.text().await.unwrap(), "error: raki is not tracked");
+    }
+
+    #[test]
+    fn keeps_wrapped_errors() {
+        let e = anyhow::Error::from(ApiError::bad_request("invalid limit"));
+        assert_eq!(ApiError::from(e), ApiError::BadRequest("invalid limit".to_string()));
+
+        let e = anyhow::anyhow!("database is locked");
+        assert_eq!(ApiError::from(e), ApiError::Unavailable);
+    }
+}
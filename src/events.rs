@@ -0,0 +1,239 @@
+use chrono::{DateTime, TimeDelta};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    api::{Cursor, DbVideo},
+    config::CONFIG,
+    oshi, sqlite,
+};
+
+/// One stream, simulcast on one or more channels
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub videos: Vec<DbVideo>,
+}
+
+impl Event {
+    pub fn primary(&self) -> &DbVideo {
+        &self.videos[0]
+    }
+
+    pub fn simulcasts(&self) -> &[DbVideo] {
+        &self.videos[1..]
+    }
+
+    /**
+     * Position of the earliest video, which events are listed in the order of
+     */
+    pub fn cursor(&self) -> Cursor {
+        let first = self
+            .videos
+            .iter()
+            .min_by(|a, b| (&a.scheduled_time, &a.id).cmp(&(&b.scheduled_time, &b.id)))
+            .unwrap_or(self.primary());
+
+        Cursor { scheduled_time: first.scheduled_time.clone(), id: first.id.clone() }
+    }
+}
+
+/**
+ * Merges videos of the same event streamed on several channels of one talent
+ */
+pub fn group_videos(videos: Vec<DbVideo>) -> Vec<Event> {
+    let manual = sqlite::get_db_video_groups().unwrap_or_else(|e| {
+        tracing::error!("failed to fetch video groups: {}", e);
+        HashMap::new()
+    });
+
+    cluster_videos(
+        videos,
+        &get_owners(),
+        &manual,
+        TimeDelta::minutes(CONFIG.simulcast.tolerance_minutes),
+        CONFIG.simulcast.title_similarity,
+    )
+}
+
+/**
+ * Maps channel ids to the talent they belong to
+ */
+fn get_owners() -> HashMap<String, String> {
+    oshi::get_talents()
+        .into_iter()
+        .flat_map(|talent| {
+            talent.channels.into_iter().map(move |channel| (channel.id, talent.name.clone()))
+        })
+        .collect()
+}
+
+fn cluster_videos(
+    videos: Vec<DbVideo>,
+    owners: &HashMap<String, String>,
+    manual: &HashMap<String, Option<String>>,
+    tolerance: TimeDelta,
+    title_similarity: Option<f64>,
+) -> Vec<Event> {
+    let mut events: Vec<Event> = vec![];
+
+    for video in videos {
+        let position = match manual.get(&video.id) {
+            // unlinked, always on its own
+            Some(None) => None,
+            Some(Some(group_id)) => events.iter().position(|event| {
+                event.videos.iter().any(|v| manual.get(&v.id) == Some(&Some(group_id.clone())))
+            }),
+            None => events.iter().position(|event| {
+                let primary = event.primary();
+
+                !manual.contains_key(&primary.id)
+                    && is_same_owner(owners, primary, &video)
+                    && !event.videos.iter().any(|v| v.channel_id == video.channel_id)
+                    && is_within(primary, &video, tolerance)
+                    && title_similarity
+                        .is_none_or(|min| similarity(&primary.title, &video.title) >= min)
+            }),
+        };
+
+        match position {
+            Some(i) => events[i].videos.push(video),
+            None => events.push(Event { videos: vec![video] }),
+        }
+    }
+
+    for event in &mut events {
+        // live entries come first so the primary url is the one to watch
+        event.videos.sort_by_key(|video| video.start_time.is_none());
+    }
+
+    events
+}
+
+fn is_same_owner(owners: &HashMap<String, String>, a: &DbVideo, b: &DbVideo) -> bool {
+    match (owners.get(&a.channel_id), owners.get(&b.channel_id)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn is_within(a: &DbVideo, b: &DbVideo, tolerance: TimeDelta) -> bool {
+    match (
+        DateTime::parse_from_rfc3339(&a.scheduled_time),
+        DateTime::parse_from_rfc3339(&b.scheduled_time),
+    ) {
+        (Ok(a), Ok(b)) => (a - b).abs() <= tolerance,
+        _ => false,
+    }
+}
+
+/**
+ * Jaccard similarity of character bigrams, which works for titles without spaces too
+ */
+fn similarity(a: &str, b: &str) -> f64 {
+    fn bigrams(s: &str) -> HashSet<(char, char)> {
+        let chars: Vec<char> = s.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+        chars.windows(2).map(|w| (w[0], w[1])).collect()
+    }
+
+    let (a, b) = (bigrams(a), bigrams(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::VideoKind, platform::Platforms};
+
+    fn video(id: &str, channel_id: &str, title: &str, scheduled_time: &str) -> DbVideo {
+        DbVideo {
+            id: id.to_string(),
+            channel_id: channel_id.to_string(),
+            channel_name: None,
+            channel_handle: None,
+            platform: Platforms::Youtube,
+            title: title.to_string(),
+            description: None,
+            thumbnails: Default::default(),
+            live_broadcast_content: "upcoming".to_string(),
+            concurrent_viewers: None,
+            duration: None,
+            premiere: false,
+            kind: VideoKind::Live,
+            collab: false,
+            scheduled_time: scheduled_time.to_string(),
+            start_time: None,
+            end_time: None,
+        }
+    }
+
+    fn owners() -> HashMap<String, String> {
+        HashMap::from([
+            ("yt".to_string(), "furi".to_string()),
+            ("tw".to_string(), "furi".to_string()),
+        ])
+    }
+
+    fn ids(events: &[Event]) -> Vec<Vec<&str>> {
+        events.iter().map(|e| e.videos.iter().map(|v| v.id.as_str()).collect()).collect()
+    }
+
+    #[test]
+    fn merges_simulcasts_of_one_owner() {
+        let mut live = video("b", "tw", "Minecraft!", "2026-01-01T12:05:00Z");
+        live.start_time = Some("2026-01-01T12:04:00Z".to_string());
+
+        let videos = vec![
+            video("a", "yt", "Minecraft", "2026-01-01T12:00:00Z"),
+            live,
+            video("c", "yt", "Zatsudan", "2026-01-01T12:10:00Z"),
+            video("d", "tw", "Karaoke", "2026-01-01T20:00:00Z"),
+        ];
+
+        let events =
+            cluster_videos(videos, &owners(), &HashMap::new(), TimeDelta::minutes(15), None);
+
+        assert_eq!(ids(&events), vec![vec!["b", "a"], vec!["c"], vec!["d"]]);
+    }
+
+    #[test]
+    fn title_similarity_is_optional() {
+        let videos = vec![
+            video("a", "yt", "Minecraft hardcore day 3", "2026-01-01T12:00:00Z"),
+            video("b", "tw", "Zatsudan", "2026-01-01T12:00:00Z"),
+        ];
+
+        let events = cluster_videos(
+            videos.clone(),
+            &owners(),
+            &HashMap::new(),
+            TimeDelta::minutes(15),
+            Some(0.5),
+        );
+        assert_eq!(ids(&events), vec![vec!["a"], vec!["b"]]);
+
+        let events =
+            cluster_videos(videos, &owners(), &HashMap::new(), TimeDelta::minutes(15), None);
+        assert_eq!(ids(&events), vec![vec!["a", "b"]]);
+    }
+
+    #[test]
+    fn manual_groups_override_matching() {
+        let videos = vec![
+            video("a", "yt", "Minecraft", "2026-01-01T12:00:00Z"),
+            video("b", "tw", "Minecraft", "2026-01-01T12:00:00Z"),
+            video("c", "other", "Collab", "2026-01-01T18:00:00Z"),
+        ];
+        let manual = HashMap::from([
+            ("b".to_string(), None),
+            ("a".to_string(), Some("manual:a".to_string())),
+            ("c".to_string(), Some("manual:a".to_string())),
+        ]);
+
+        let events = cluster_videos(videos, &owners(), &manual, TimeDelta::minutes(15), None);
+
+        assert_eq!(ids(&events), vec![vec!["a", "c"], vec!["b"]]);
+    }
+}
@@ -0,0 +1,326 @@
+use anyhow::{Result, anyhow};
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, header::ACCEPT_LANGUAGE, request::Parts},
+};
+use serde::Deserialize;
+use std::{fmt, str::FromStr};
+
+use crate::{error::ApiError, time::TimePeriod};
+
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub enum Locale {
+    #[default]
+    En,
+    Ja,
+}
+
+/// Singular and plural forms of a unit, `{n}` being replaced with the count
+struct Plural {
+    one: &'static str,
+    other: &'static str,
+}
+
+/// Strings shown in text pages
+pub struct Catalogue {
+    /// strftime format of absolute dates, ending with the zone abbreviation
+    pub date_format: &'static str,
+    /// Less than a minute ago
+    pub now: &'static str,
+    /// Less than a minute from now
+    pub within_a_minute: &'static str,
+    /// A stream whose scheduled time has passed but that hasn't started yet
+    pub starting: &'static str,
+    in_future: &'static str,
+    in_past: &'static str,
+    /// Between the units of a duration
+    separator: &'static str,
+    weeks: Plural,
+    days: Plural,
+    hours: Plural,
+    minutes: Plural,
+    pub upcoming: &'static str,
+    pub live: &'static str,
+    pub ended: &'static str,
+    pub collab: &'static str,
+    pub channel: &'static str,
+    pub url: &'static str,
+    pub started: &'static str,
+    pub scheduled: &'static str,
+    pub viewers: &'static str,
+    pub aliases: &'static str,
+    pub groups: &'static str,
+    pub subs: &'static str,
+    pub no_upcoming_streams: &'static str,
+    pub no_channels: &'static str,
+    pub no_groups: &'static str,
+    more: &'static str,
+    not_tracked: &'static str,
+    group_not_tracked: &'static str,
+    did_you_mean: &'static str,
+}
+
+static EN: Catalogue = Catalogue {
+    date_format: "%Y-%m-%d %H:%M %Z",
+    now: "now",
+    within_a_minute: "in <1 minute",
+    starting: "starting",
+    in_future: "in {}",
+    in_past: "{} ago",
+    separator: " ",
+    weeks: Plural { one: "{n} week", other: "{n} weeks" },
+    days: Plural { one: "{n} day", other: "{n} days" },
+    hours: Plural { one: "{n} hour", other: "{n} hours" },
+    minutes: Plural { one: "{n} minute", other: "{n} minutes" },
+    upcoming: "upcoming",
+    live: "live",
+    ended: "ended",
+    collab: "collab",
+    channel: "channel",
+    url: "url",
+    started: "started",
+    scheduled: "scheduled",
+    viewers: "viewers",
+    aliases: "aliases",
+    groups: "groups",
+    subs: "subs",
+    no_upcoming_streams: "no upcoming streams",
+    no_channels: "no channels found",
+    no_groups: "no groups found",
+    more: "…and {n} more, see {link}",
+    not_tracked: "{name} is not tracked",
+    group_not_tracked: "group {name} is not tracked",
+    did_you_mean: "{message}, did you mean {name}?",
+};
+
+static JA: Catalogue = Catalogue {
+    date_format: "%Y年%-m月%-d日 %H:%M %Z",
+    now: "たった今",
+    within_a_minute: "1分以内",
+    starting: "まもなく開始",
+    in_future: "{}後",
+    in_past: "{}前",
+    separator: "",
+    weeks: Plural { one: "{n}週間", other: "{n}週間" },
+    days: Plural { one: "{n}日", other: "{n}日" },
+    hours: Plural { one: "{n}時間", other: "{n}時間" },
+    minutes: Plural { one: "{n}分", other: "{n}分" },
+    upcoming: "予定",
+    live: "配信中",
+    ended: "終了",
+    collab: "コラボ",
+    channel: "チャンネル",
+    url: "URL",
+    started: "開始",
+    scheduled: "予定",
+    viewers: "視聴者",
+    aliases: "別名",
+    groups: "グループ",
+    subs: "登録者",
+    no_upcoming_streams: "予定されている配信はありません",
+    no_channels: "チャンネルが見つかりません",
+    no_groups: "グループが見つかりません",
+    more: "…ほか{n}件、続きは {link}",
+    not_tracked: "{name} は登録されていません",
+    group_not_tracked: "グループ {name} は登録されていません",
+    did_you_mean: "{message}。もしかして {name}？",
+};
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Ja];
+
+    pub fn messages(&self) -> &'static Catalogue {
+        match self {
+            Locale::En => &EN,
+            Locale::Ja => &JA,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Ja => "ja",
+        }
+    }
+
+    /**
+     * The supported locale with the highest weight in an `Accept-Language` header (RFC 9110 §12.5.4)
+     */
+    pub fn from_accept_language(header: &str) -> Option<Locale> {
+        header
+            .split(',')
+            .filter_map(|range| {
+                let mut params = range.split(';').map(str::trim);
+                let tag = params.next()?;
+                let q = params
+                    .find_map(|param| param.strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+
+                // `ja-JP` falls back to `ja`, `*` to the default
+                let primary = tag.split('-').next()?;
+                let locale = match primary {
+                    "*" => Some(Locale::default()),
+                    _ => primary.parse().ok(),
+                };
+
+                locale.filter(|_| q > 0.0).map(|locale| (locale, q))
+            })
+            .fold(None, |best: Option<(Locale, f32)>, (locale, q)| match best {
+                Some((_, best_q)) if best_q >= q => best,
+                _ => Some((locale, q)),
+            })
+            .map(|(locale, _)| locale)
+    }
+
+    /**
+     * A duration like `3 hours 20 minutes`, pluralising each unit
+     */
+    pub fn duration(&self, periods: &[TimePeriod]) -> String {
+        let messages = self.messages();
+
+        periods
+            .iter()
+            .map(|period| {
+                let (n, plural) = match *period {
+                    TimePeriod::Weeks(n) => (n, &messages.weeks),
+                    TimePeriod::Days(n) => (n, &messages.days),
+                    TimePeriod::Hours(n) => (n, &messages.hours),
+                    TimePeriod::Minutes(n) => (n, &messages.minutes),
+                };
+
+                self.plural(n, plural).replace("{n}", &n.to_string())
+            })
+            .collect::<Vec<_>>()
+            .join(messages.separator)
+    }
+
+    fn plural(&self, n: i64, plural: &Plural) -> &'static str {
+        match (self, n) {
+            (Locale::En, 1) => plural.one,
+            _ => plural.other,
+        }
+    }
+
+    /**
+     * A duration relative to now, `future` telling which side of it
+     */
+    pub fn relative(&self, duration: &str, future: bool) -> String {
+        let messages = self.messages();
+
+        match future {
+            true => messages.in_future.replace("{}", duration),
+            false => messages.in_past.replace("{}", duration),
+        }
+    }
+
+    pub fn more(&self, count: usize, link: &str) -> String {
+        self.messages().more.replace("{n}", &count.to_string()).replace("{link}", link)
+    }
+
+    pub fn not_tracked(&self, name: &str, suggestion: Option<&str>) -> String {
+        self.suggest(self.messages().not_tracked.replace("{name}", name), suggestion)
+    }
+
+    pub fn group_not_tracked(&self, name: &str) -> String {
+        self.messages().group_not_tracked.replace("{name}", name)
+    }
+
+    fn suggest(&self, message: String, suggestion: Option<&str>) -> String {
+        match suggestion {
+            Some(name) => {
+                self.messages().did_you_mean.replace("{message}", &message).replace("{name}", name)
+            }
+            None => message,
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Locale::ALL
+            .into_iter()
+            .find(|locale| s.eq_ignore_ascii_case(locale.as_str()))
+            .ok_or_else(|| anyhow!("unsupported language: {s}, expected en or ja"))
+    }
+}
+
+#[derive(Deserialize)]
+struct LangQuery {
+    lang: Option<String>,
+}
+
+/**
+ * The locale asked for with `?lang=`, or else with the `Accept-Language` header
+ */
+pub fn negotiate(query: Option<&str>, headers: &HeaderMap) -> Result<Locale, ApiError> {
+    let query = serde_urlencoded::from_str::<LangQuery>(query.unwrap_or_default())
+        .map_err(ApiError::bad_request)?;
+
+    if let Some(lang) = query.lang {
+        return lang.parse().map_err(ApiError::bad_request);
+    }
+
+    Ok(from_headers(headers).unwrap_or_default())
+}
+
+pub fn from_headers(headers: &HeaderMap) -> Option<Locale> {
+    headers
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|val| val.to_str().ok())
+        .and_then(Locale::from_accept_language)
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Locale {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        negotiate(parts.uri.query(), &parts.headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighs_accept_language() {
+        assert_eq!(Locale::from_accept_language("ja"), Some(Locale::Ja));
+        assert_eq!(Locale::from_accept_language("ja-JP,ja;q=0.9,en;q=0.8"), Some(Locale::Ja));
+        assert_eq!(Locale::from_accept_language("fr, en;q=0.5, ja;q=0.7"), Some(Locale::Ja));
+        assert_eq!(Locale::from_accept_language("en-US, ja;q=0"), Some(Locale::En));
+        assert_eq!(Locale::from_accept_language("fr, *;q=0.1"), Some(Locale::En));
+        assert_eq!(Locale::from_accept_language("fr, de"), None);
+    }
+
+    #[test]
+    fn pluralises_units() {
+        let periods = [TimePeriod::Hours(1), TimePeriod::Minutes(20)];
+
+        assert_eq!(Locale::En.duration(&periods), "1 hour 20 minutes");
+        assert_eq!(
+            Locale::En.duration(&[TimePeriod::Weeks(2), TimePeriod::Days(1)]),
+            "2 weeks 1 day"
+        );
+        assert_eq!(Locale::Ja.duration(&periods), "1時間20分");
+
+        assert_eq!(Locale::En.relative("1 hour", true), "in 1 hour");
+        assert_eq!(Locale::Ja.relative("1時間", false), "1時間前");
+    }
+
+    #[test]
+    fn suggests_aliases() {
+        assert_eq!(
+            Locale::En.not_tracked("fury", Some("furi")),
+            "fury is not tracked, did you mean furi?"
+        );
+        assert_eq!(Locale::Ja.not_tracked("fury", None), "fury は登録されていません");
+    }
+}
@@ -1,67 +1,61 @@
+mod admin;
 mod api;
 mod colors;
+mod compression;
+mod conditional;
 mod config;
+mod error;
+mod events;
+mod i18n;
+mod metrics;
+mod negotiate;
+mod oshi;
 mod pages;
+mod platform;
+mod query;
 mod scheduler;
 mod sqlite;
+mod status;
+#[cfg(test)]
+mod testing;
+mod text;
 mod time;
+mod twitch;
 mod utils;
 mod youtube;
 
 use anyhow::Result;
 use axum::{
-    Router,
-    extract::Query,
-    http::{HeaderMap, HeaderValue, StatusCode, header},
-    response::Redirect,
+    Json, Router,
+    extract::{Query, rejection::QueryRejection},
+    http::{HeaderMap, HeaderValue, StatusCode, Uri, header},
+    middleware,
+    response::{IntoResponse, Redirect, Response},
     routing::get,
 };
+use chrono::Utc;
+use colors::ColorMode;
 use config::CONFIG;
 use dotenvy::dotenv;
-use pages::{PageContext, Pages, Render};
-use reqwest::{
-    Method,
-    header::{ACCEPT, USER_AGENT},
-};
+use error::ApiError;
+use i18n::Locale;
+use negotiate::ResponseFormat;
+use pages::{Format, PageContext, Pages, Rendered, table::Fields};
+use query::RootQuery;
+use reqwest::Method;
 use std::{
-    collections::HashMap,
     net::{Ipv4Addr, SocketAddr},
     str::FromStr,
 };
+use text::TextOptions;
 use tokio::net::TcpListener;
 use tower_http::cors;
-use utils::is_term;
-
-enum ResponseFormat {
-    Text,
-    Json,
-    Browser,
-}
 
-fn negotiate(headers: &HeaderMap) -> ResponseFormat {
-    let user_agent = headers.get(USER_AGENT);
+/// Version 0.0.4 of the Prometheus text format
+const METRICS_HEADER: HeaderValue = HeaderValue::from_static("text/plain; version=0.0.4");
 
-    if let Some(val) = headers.get(ACCEPT)
-        && let Ok(s) = val.to_str()
-    {
-        if s.contains("application/json") {
-            return ResponseFormat::Json;
-        }
-        if s.contains("text/plain") || is_term(user_agent) {
-            return ResponseFormat::Text;
-        }
-    }
-
-    if is_term(user_agent) {
-        return ResponseFormat::Text;
-    }
-
-    ResponseFormat::Browser
-}
-
-const CACHE_CONTROL_VALUE: HeaderValue = HeaderValue::from_static("public, max-age=60");
-const JSON_HEADER: HeaderValue = HeaderValue::from_static("application/json");
-const TEXT_HEADER: HeaderValue = HeaderValue::from_static("text/plain");
+/// Extensions a page can be asked for in, as with `?format=`
+const EXTENSIONS: [&str; 7] = ["json", "txt", "csv", "tsv", "yaml", "yml", "md"];
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -69,18 +63,55 @@ async fn main() -> Result<()> {
     tracing_subscriber::fmt().with_max_level(CONFIG.server.log_level).init();
 
     sqlite::init_db();
+    oshi::init_oshi().await;
     scheduler::init_scheduler().await.expect("failed to init scheduler");
 
     let cors = cors::CorsLayer::new() //
-        .allow_methods([Method::GET])
+        .allow_methods([Method::GET, Method::HEAD])
         .allow_origin(cors::Any);
 
-    let router = Router::new()
-        .fallback(Redirect::temporary(&CONFIG.browser_redirect))
-        .route("/health", get(get_health))
+    let mut pages = Router::new()
         .route("/", get(get_root))
         .route("/list", get(get_list))
-        .layer(cors);
+        .route("/groups", get(get_groups))
+        .route("/status", get(get_status));
+
+    for extension in EXTENSIONS {
+        pages = pages
+            .route(&format!("/index.{extension}"), get(get_root))
+            .route(&format!("/list.{extension}"), get(get_list))
+            .route(&format!("/groups.{extension}"), get(get_groups));
+    }
+
+    // pages answer errors in the format they negotiate
+    let pages = pages
+        .layer(middleware::from_fn(error::render_errors))
+        .layer(middleware::from_fn(negotiate::vary))
+        .layer(middleware::from_fn(conditional::not_modified));
+
+    // notifications trigger api calls, so they are only accepted when signed
+    let websub = match (&CONFIG.websub.callback_url, &CONFIG.websub.secret) {
+        (Some(_), Some(secret)) => youtube::websub::router(secret.clone()),
+        (Some(_), None) => {
+            tracing::warn!("websub is disabled, WEBSUB_SECRET is not set");
+            Router::new()
+        }
+        _ => Router::new(),
+    };
+
+    let router = Router::new()
+        .fallback(Redirect::temporary(CONFIG.browser_redirect))
+        .route("/health", get(get_health))
+        .route("/health/live", get(get_health))
+        .route("/health/ready", get(get_ready))
+        .route("/metrics", get(get_metrics))
+        .merge(pages)
+        .merge(websub)
+        .nest("/admin", admin::router(CONFIG.admin.token.clone()))
+        .nest(api::v1::BASE_PATH, api::v1::router())
+        .layer(compression::layer(CONFIG.server.compression_min_bytes))
+        .layer(cors)
+        .layer(middleware::from_fn(metrics::track_requests));
 
     let host = Ipv4Addr::from_str(&CONFIG.server.host).expect("invalid host");
     let socket = SocketAddr::from((host, CONFIG.server.port));
@@ -98,99 +129,169 @@ async fn main() -> Result<()> {
 }
 
 // GET /health
-async fn get_health() -> StatusCode {
-    StatusCode::OK
+// GET /health/live
+async fn get_health() -> (StatusCode, HeaderMap, String) {
+    health_response(status::liveness(Utc::now()))
 }
 
-// GET /
-async fn get_root(
-    query: Query<HashMap<String, String>>,
-    req_headers: HeaderMap,
-) -> impl axum::response::IntoResponse {
+// GET /health/ready
+async fn get_ready() -> (StatusCode, HeaderMap, String) {
+    health_response(status::readiness(Utc::now()))
+}
+
+/**
+ * A line per check, answered with a 503 when any of them failed
+ */
+fn health_response(checks: Vec<status::Check>) -> (StatusCode, HeaderMap, String) {
     let mut res_headers = HeaderMap::new();
+    res_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+
+    let code = match checks.iter().all(|check| check.ok) {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (code, res_headers, status::format_checks(&checks, ColorMode::Never))
+}
 
-    match negotiate(&req_headers) {
-        ResponseFormat::Browser => {
-            res_headers.insert(header::LOCATION, CONFIG.browser_redirect.parse().unwrap());
-            (StatusCode::TEMPORARY_REDIRECT, res_headers, "Redirecting to GitHub...".to_string())
+// GET /status
+async fn get_status(format: ResponseFormat, text: TextOptions) -> Result<Response, ApiError> {
+    let status = status::Status::new(Utc::now());
+    let no_store = [(header::CACHE_CONTROL, HeaderValue::from_static("no-store"))];
+
+    match format {
+        ResponseFormat::Text => Ok((no_store, status.to_text(text.color)).into_response()),
+        ResponseFormat::Json | ResponseFormat::Browser => {
+            Ok((no_store, Json(status)).into_response())
         }
-        format => {
-            let oshi = query.get("oshi").cloned();
-            let id: &Option<String> = match &oshi {
-                None => &Some("all".to_string()),
-                Some(alias) if !CONFIG.oshi.contains_key(alias) => &Some("invalid".to_string()),
-                Some(_) => &oshi.clone().and_then(|alias| {
-                    CONFIG //
-                        .oshi
-                        .get(&alias)
-                        .cloned()
-                }),
-            };
-
-            let ctx = PageContext { channel_id: id.clone() };
-            res_headers.insert(header::CACHE_CONTROL, CACHE_CONTROL_VALUE);
-
-            match format {
-                ResponseFormat::Json => {
-                    let content = Pages::Root //
-                        .render_json(ctx)
-                        .await
-                        .expect("failed to render json page");
-
-                    res_headers.insert(header::CONTENT_TYPE, JSON_HEADER);
-
-                    (StatusCode::OK, res_headers, content)
-                }
-                _ => {
-                    let content = Pages::Root //
-                        .render_text(ctx)
-                        .await
-                        .expect("failed to render text page");
-
-                    res_headers.insert(header::CONTENT_TYPE, TEXT_HEADER);
-
-                    (StatusCode::OK, res_headers, content)
-                }
-            }
+        _ => {
+            Err(ApiError::NotAcceptable("the status can only be shown as text or json".to_string()))
         }
     }
 }
 
-// GET /list
-async fn get_list(req_headers: HeaderMap) -> impl axum::response::IntoResponse {
+// GET /metrics
+async fn get_metrics() -> Result<(HeaderMap, String), ApiError> {
     let mut res_headers = HeaderMap::new();
+    res_headers.insert(header::CONTENT_TYPE, METRICS_HEADER);
 
-    match negotiate(&req_headers) {
-        ResponseFormat::Browser => {
-            res_headers.insert(header::LOCATION, CONFIG.browser_redirect.parse().unwrap());
-            (StatusCode::TEMPORARY_REDIRECT, res_headers, "Redirecting to GitHub...".to_string())
-        }
-        format => {
-            let ctx = PageContext { channel_id: None };
-            res_headers.insert(header::CACHE_CONTROL, CACHE_CONTROL_VALUE);
-
-            match format {
-                ResponseFormat::Json => {
-                    let content = Pages::List //
-                        .render_json(ctx)
-                        .await
-                        .expect("failed to render json page");
-
-                    res_headers.insert(header::CONTENT_TYPE, JSON_HEADER);
-
-                    (StatusCode::OK, res_headers, content)
-                }
-                _ => {
-                    let content = Pages::Root //
-                        .render_text(ctx)
-                        .await
-                        .expect("failed to render text page");
-
-                    res_headers.insert(header::CONTENT_TYPE, TEXT_HEADER);
-
-                    (StatusCode::OK, res_headers, content)
-                }
-            }
+    Ok((res_headers, metrics::render()?))
+}
+
+// GET /
+async fn get_root(
+    format: ResponseFormat,
+    query: Result<Query<RootQuery>, QueryRejection>,
+    uri: Uri,
+    req_headers: HeaderMap,
+) -> Result<(StatusCode, HeaderMap, String), ApiError> {
+    // browsers are redirected whatever they ask for
+    let ctx = match format {
+        ResponseFormat::Browser => PageContext::default(),
+        _ => {
+            let Query(query) = query?;
+            let ctx = PageContext::try_from(query.with_headers(&req_headers))?;
+            let forced = negotiate::get_override(&uri)?.and_then(page_format);
+
+            PageContext { format: forced, ..ctx }
         }
+    };
+
+    render_page(Pages::Root, format, ctx).await
+}
+
+// GET /list
+async fn get_list(
+    format: ResponseFormat,
+    locale: Locale,
+    text: TextOptions,
+    Fields(fields): Fields,
+) -> Result<(StatusCode, HeaderMap, String), ApiError> {
+    let ctx = PageContext { locale, text, fields, ..Default::default() };
+
+    render_page(Pages::List, format, ctx).await
+}
+
+// GET /groups
+async fn get_groups(
+    format: ResponseFormat,
+    locale: Locale,
+    text: TextOptions,
+    Fields(fields): Fields,
+) -> Result<(StatusCode, HeaderMap, String), ApiError> {
+    let ctx = PageContext { locale, text, fields, ..Default::default() };
+
+    render_page(Pages::Groups, format, ctx).await
+}
+
+/**
+ * Renders a page in the negotiated format, linking to the next page of paginated listings
+ */
+async fn render_page(
+    page: Pages,
+    format: ResponseFormat,
+    ctx: PageContext,
+) -> Result<(StatusCode, HeaderMap, String), ApiError> {
+    let mut res_headers = HeaderMap::new();
+
+    let Some(format) = page_format(format) else {
+        res_headers.insert(header::LOCATION, HeaderValue::from_static(CONFIG.browser_redirect));
+        return Ok((
+            StatusCode::TEMPORARY_REDIRECT,
+            res_headers,
+            "Redirecting to GitHub...".to_string(),
+        ));
+    };
+
+    let content = page.render(format, ctx).await?;
+
+    // text pages link to the next page in their body
+    if let Some(next) = &content.next
+        && format != Format::Text
+        && let Ok(link) = HeaderValue::from_str(&format!("<{next}>; rel=\"next\""))
+    {
+        res_headers.insert(header::LINK, link);
+    }
+
+    res_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(format.content_type()));
+    insert_cache_headers(&mut res_headers, &content);
+
+    Ok((StatusCode::OK, res_headers, content.body))
+}
+
+/**
+ * The format a page is rendered in, none for browsers which are redirected
+ */
+fn page_format(format: ResponseFormat) -> Option<Format> {
+    match format {
+        ResponseFormat::Browser => None,
+        ResponseFormat::Text => Some(Format::Text),
+        ResponseFormat::Json => Some(Format::Json),
+        ResponseFormat::Csv => Some(Format::Csv),
+        ResponseFormat::Tsv => Some(Format::Tsv),
+        ResponseFormat::Yaml => Some(Format::Yaml),
+        ResponseFormat::Markdown => Some(Format::Markdown),
+    }
+}
+
+/**
+ * Lets clients keep a page until videos are next looked up, and revalidate it afterwards
+ */
+fn insert_cache_headers(res_headers: &mut HeaderMap, content: &Rendered) {
+    let now = Utc::now();
+    let max_age = scheduler::next_refresh(now).map_or(0, |next| (next - now).num_seconds().max(0));
+
+    if let Ok(cache_control) = HeaderValue::from_str(&format!("public, max-age={max_age}")) {
+        res_headers.insert(header::CACHE_CONTROL, cache_control);
+    }
+
+    if let Some(etag) = content.etag.as_deref().and_then(|etag| HeaderValue::from_str(etag).ok()) {
+        res_headers.insert(header::ETAG, etag);
+    }
+
+    if let Some(modified) = content.modified
+        && let Ok(modified) = HeaderValue::from_str(&httpdate::fmt_http_date(modified))
+    {
+        res_headers.insert(header::LAST_MODIFIED, modified);
     }
 }
@@ -0,0 +1,210 @@
+use anyhow::Result;
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{HeaderValue, header},
+    middleware::Next,
+    response::Response,
+};
+use prometheus::{
+    Encoder, Histogram, HistogramVec, IntCounterVec, IntGaugeVec, TextEncoder, register_histogram,
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec,
+};
+use std::{sync::LazyLock, time::Instant};
+
+use crate::sqlite;
+
+/// Buckets of scheduler jobs, which can take minutes with many channels
+const JOB_BUCKETS: &[f64] = &[0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+/// Buckets of db lock waits, which are usually well under a millisecond
+const LOCK_BUCKETS: &[f64] = &[0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "http_requests_total",
+        "Requests answered, by route, method, format and status",
+        &["route", "method", "format", "status"]
+    )
+    .unwrap()
+});
+
+static HTTP_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "http_request_duration_seconds",
+        "Time taken to answer requests, by route and format",
+        &["route", "format"]
+    )
+    .unwrap()
+});
+
+static PAGE_CACHE_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "page_cache_requests_total",
+        "Page cache lookups, by page, format and result",
+        &["page", "format", "result"]
+    )
+    .unwrap()
+});
+
+static JOB_RUNS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "scheduler_job_runs_total",
+        "Scheduler job runs, by job and outcome",
+        &["job", "outcome"]
+    )
+    .unwrap()
+});
+
+static JOB_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "scheduler_job_duration_seconds",
+        "Time taken by scheduler jobs, by job",
+        &["job"],
+        JOB_BUCKETS.to_vec()
+    )
+    .unwrap()
+});
+
+static YOUTUBE_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "youtube_requests_total",
+        "Calls to YouTube, by endpoint and outcome",
+        &["endpoint", "outcome"]
+    )
+    .unwrap()
+});
+
+static VIDEOS_TRACKED: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!("videos_tracked", "Stored videos, by status", &["status"]).unwrap()
+});
+
+static DB_LOCK_WAIT: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "db_lock_wait_seconds",
+        "Time spent waiting for the db connection",
+        LOCK_BUCKETS.to_vec()
+    )
+    .unwrap()
+});
+
+fn outcome(success: bool) -> &'static str {
+    match success {
+        true => "success",
+        false => "error",
+    }
+}
+
+pub fn record_cache_lookup(page: &str, format: &str, hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    PAGE_CACHE_REQUESTS.with_label_values(&[page, format, result]).inc();
+}
+
+pub fn record_job(job: &str, seconds: f64, success: bool) {
+    JOB_RUNS.with_label_values(&[job, outcome(success)]).inc();
+    JOB_DURATION.with_label_values(&[job]).observe(seconds);
+}
+
+pub fn record_youtube_call(endpoint: &str, success: bool) {
+    YOUTUBE_REQUESTS.with_label_values(&[endpoint, outcome(success)]).inc();
+}
+
+pub fn record_db_lock_wait(seconds: f64) {
+    DB_LOCK_WAIT.observe(seconds);
+}
+
+/**
+ * Short name of a response's media type, like `json` for `application/problem+json`
+ */
+fn format_label(content_type: Option<&HeaderValue>) -> &'static str {
+    let Some(content_type) = content_type.and_then(|val| val.to_str().ok()) else {
+        return "none";
+    };
+
+    match content_type.split(';').next().unwrap_or_default().trim() {
+        "text/plain" => "text",
+        "application/json" | "application/problem+json" => "json",
+        "text/csv" => "csv",
+        "text/tab-separated-values" => "tsv",
+        "application/yaml" => "yaml",
+        "text/markdown" => "markdown",
+        "text/html" => "html",
+        _ => "other",
+    }
+}
+
+/**
+ * Counts and times requests by the route they matched, so ids in paths don't make new series
+ */
+pub async fn track_requests(req: Request, next: Next) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched".to_string(), |path| path.as_str().to_string());
+    let method = req.method().to_string();
+    let started = Instant::now();
+
+    let res = next.run(req).await;
+
+    let format = format_label(res.headers().get(header::CONTENT_TYPE));
+    let status = res.status().as_u16().to_string();
+
+    HTTP_REQUESTS.with_label_values(&[route.as_str(), &method, format, &status]).inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[route.as_str(), format])
+        .observe(started.elapsed().as_secs_f64());
+
+    res
+}
+
+/**
+ * Every metric in the Prometheus text format, counting stored videos on the way
+ */
+pub fn render() -> Result<String> {
+    let counts = sqlite::count_db_videos()?;
+
+    for status in ["upcoming", "live", "ended"] {
+        let count = counts.get(status).copied().unwrap_or_default();
+        VIDEOS_TRACKED.with_label_values(&[status]).set(count);
+    }
+
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+
+    Ok(String::from_utf8(buffer)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, middleware, routing::get};
+
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn counts_requests_by_route() {
+        let router = Router::new()
+            .route("/metrics-test/{id}", get(|| async { "ok" }))
+            .layer(middleware::from_fn(track_requests));
+
+        let addr = testing::serve(router).await;
+
+        for id in ["a", "b"] {
+            reqwest::get(format!("http://{addr}/metrics-test/{id}")).await.unwrap();
+        }
+
+        let count =
+            HTTP_REQUESTS.with_label_values(&["/metrics-test/{id}", "GET", "text", "200"]).get();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn labels_formats() {
+        let label = |val: &'static str| format_label(Some(&HeaderValue::from_static(val)));
+
+        assert_eq!(label("text/plain"), "text");
+        assert_eq!(label("application/problem+json"), "json");
+        assert_eq!(label("text/csv; charset=utf-8"), "csv");
+        assert_eq!(label("image/png"), "other");
+        assert_eq!(format_label(None), "none");
+    }
+}
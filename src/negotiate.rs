@@ -0,0 +1,350 @@
+use axum::{
+    extract::{FromRequestParts, Request},
+    http::{
+        HeaderMap, HeaderValue, Uri,
+        header::{ACCEPT, USER_AGENT, VARY},
+        request::Parts,
+    },
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+
+use crate::{config::CONFIG, error::ApiError};
+
+const VARY_VALUE: HeaderValue =
+    HeaderValue::from_static("Accept, Accept-Language, User-Agent, TZ, No-Color");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResponseFormat {
+    Text,
+    Json,
+    /// Redirected to the repository
+    Browser,
+    Csv,
+    Tsv,
+    Yaml,
+    Markdown,
+}
+
+impl ResponseFormat {
+    const ALL: [ResponseFormat; 7] = [
+        ResponseFormat::Text,
+        ResponseFormat::Json,
+        ResponseFormat::Browser,
+        ResponseFormat::Csv,
+        ResponseFormat::Tsv,
+        ResponseFormat::Yaml,
+        ResponseFormat::Markdown,
+    ];
+
+    fn media_type(&self) -> (&'static str, &'static str) {
+        match self {
+            ResponseFormat::Text => ("text", "plain"),
+            ResponseFormat::Json => ("application", "json"),
+            ResponseFormat::Browser => ("text", "html"),
+            ResponseFormat::Csv => ("text", "csv"),
+            ResponseFormat::Tsv => ("text", "tab-separated-values"),
+            ResponseFormat::Yaml => ("application", "yaml"),
+            ResponseFormat::Markdown => ("text", "markdown"),
+        }
+    }
+
+    /**
+     * The format forced by a `?format=` value or a file extension
+     */
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(ResponseFormat::Json),
+            "txt" | "text" => Some(ResponseFormat::Text),
+            "csv" => Some(ResponseFormat::Csv),
+            "tsv" => Some(ResponseFormat::Tsv),
+            "yaml" | "yml" => Some(ResponseFormat::Yaml),
+            "md" | "markdown" => Some(ResponseFormat::Markdown),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FormatQuery {
+    format: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ResponseFormat {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        negotiate(&parts.uri, &parts.headers)
+    }
+}
+
+/**
+ * Picks the format of a response, from `?format=`, the file extension, then the `Accept` header.
+ * The user agent only breaks ties, so terminals get text when they accept anything,
+ * and clients accepting none of the formats are answered with a 406
+ */
+pub fn negotiate(uri: &Uri, headers: &HeaderMap) -> Result<ResponseFormat, ApiError> {
+    if let Some(format) = get_override(uri)? {
+        return Ok(format);
+    }
+
+    let accept = headers.get(ACCEPT).and_then(|val| val.to_str().ok());
+
+    select(accept, || is_term(headers.get(USER_AGENT), &CONFIG.server.terminal_agents))
+}
+
+/**
+ * The format forced by `?format=` or the file extension, if any
+ */
+pub fn get_override(uri: &Uri) -> Result<Option<ResponseFormat>, ApiError> {
+    let query = serde_urlencoded::from_str::<FormatQuery>(uri.query().unwrap_or_default())
+        .map_err(ApiError::bad_request)?;
+
+    if let Some(name) = query.format {
+        return match ResponseFormat::from_name(&name) {
+            Some(format) => Ok(Some(format)),
+            None => Err(ApiError::BadRequest(format!(
+                "invalid format: {name}, expected json, text, csv, tsv, yaml or markdown"
+            ))),
+        };
+    }
+
+    let extension = uri
+        .path()
+        .rsplit('/')
+        .next()
+        .and_then(|file| file.rsplit_once('.'))
+        .map(|(_, extension)| extension);
+
+    Ok(extension.and_then(ResponseFormat::from_name))
+}
+
+/// A media range of an `Accept` header, like `text/*;q=0.5`
+#[derive(Debug, PartialEq)]
+struct MediaRange {
+    kind: String,
+    subtype: String,
+    q: f32,
+}
+
+impl MediaRange {
+    /**
+     * How closely the range matches a media type, `None` when it doesn't
+     */
+    fn specificity(&self, (kind, subtype): (&str, &str)) -> Option<u8> {
+        match (self.kind.as_str(), self.subtype.as_str()) {
+            ("*", "*") => Some(0),
+            (k, "*") if k == kind => Some(1),
+            (k, s) if k == kind && s == subtype => Some(2),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Parses the media ranges of an `Accept` header (RFC 9110 §12.5.1), skipping malformed ones
+ */
+fn parse_accept(header: &str) -> Vec<MediaRange> {
+    header
+        .split(',')
+        .filter_map(|range| {
+            let mut params = range.split(';').map(str::trim);
+            let (kind, subtype) = params.next()?.split_once('/')?;
+
+            if kind.is_empty() || subtype.is_empty() || (kind == "*" && subtype != "*") {
+                return None;
+            }
+
+            let mut q = 1.0;
+            for param in params {
+                if let Some((name, value)) = param.split_once('=')
+                    && name.trim().eq_ignore_ascii_case("q")
+                {
+                    q = value.trim().parse::<f32>().ok().filter(|q| (0.0..=1.0).contains(q))?;
+                }
+            }
+
+            Some(MediaRange {
+                kind: kind.to_ascii_lowercase(),
+                subtype: subtype.to_ascii_lowercase(),
+                q,
+            })
+        })
+        .collect()
+}
+
+/**
+ * The weight a client gives to a format, from the most specific range that matches it
+ */
+fn quality(ranges: &[MediaRange], format: ResponseFormat) -> f32 {
+    ranges
+        .iter()
+        .filter_map(|range| Some((range.specificity(format.media_type())?, range.q)))
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(0.0, |(_, q)| q)
+}
+
+fn select(
+    accept: Option<&str>,
+    is_term: impl FnOnce() -> bool,
+) -> Result<ResponseFormat, ApiError> {
+    let mut ranges = parse_accept(accept.unwrap_or("*/*"));
+
+    // a header without a single valid range says nothing, like a missing one
+    if ranges.is_empty() {
+        ranges = parse_accept("*/*");
+    }
+
+    let weighted = ResponseFormat::ALL.map(|format| (format, quality(&ranges, format)));
+    let best = weighted.iter().map(|(_, q)| *q).fold(0.0, f32::max);
+
+    if best == 0.0 {
+        return Err(ApiError::NotAcceptable(format!(
+            "unsupported accept: {}, expected text/plain, application/json, text/html, text/csv, \
+             text/tab-separated-values, application/yaml or text/markdown",
+            accept.unwrap_or_default()
+        )));
+    }
+
+    let tied: Vec<ResponseFormat> =
+        weighted.iter().filter(|(_, q)| *q == best).map(|(format, _)| *format).collect();
+
+    if let [format] = tied.as_slice() {
+        return Ok(*format);
+    }
+
+    // tabular formats come last, so wildcards never pick them
+    let preference = match is_term() {
+        true => [ResponseFormat::Text, ResponseFormat::Json, ResponseFormat::Browser],
+        false => [ResponseFormat::Browser, ResponseFormat::Json, ResponseFormat::Text],
+    }
+    .into_iter()
+    .chain([
+        ResponseFormat::Csv,
+        ResponseFormat::Tsv,
+        ResponseFormat::Yaml,
+        ResponseFormat::Markdown,
+    ])
+    .collect::<Vec<_>>();
+
+    Ok(preference.iter().copied().find(|format| tied.contains(format)).unwrap_or(preference[0]))
+}
+
+/**
+ * Whether a product of the user agent, like `curl/8.5.0`, contains one of the patterns
+ */
+pub fn is_term(user_agent: Option<&HeaderValue>, patterns: &[String]) -> bool {
+    let user_agent = match user_agent {
+        Some(ua) => match ua.to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                tracing::debug!("invalid user agent header");
+                return false;
+            }
+        },
+        None => {
+            tracing::debug!("no user agent provided");
+            return false;
+        }
+    };
+
+    tracing::debug!("user agent: {}", user_agent);
+
+    user_agent
+        .split_whitespace()
+        .filter_map(|token| token.split_once('/').map(|(product, _)| product.to_lowercase()))
+        .any(|product| patterns.iter().any(|pattern| product.contains(&pattern.to_lowercase())))
+}
+
+/**
+ * Tells caches that negotiated responses depend on the client
+ */
+pub async fn vary(req: Request, next: Next) -> Response {
+    let mut res = next.run(req).await;
+    res.headers_mut().insert(VARY, VARY_VALUE);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighs_media_ranges() {
+        let select = |accept| select(Some(accept), || false).unwrap();
+
+        assert_eq!(select("application/json"), ResponseFormat::Json);
+        assert_eq!(select("text/plain;q=0.9, application/json;q=0.5"), ResponseFormat::Text);
+        assert_eq!(select("text/*;q=0.2, application/json;q=0.3"), ResponseFormat::Json);
+        assert_eq!(
+            select("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"),
+            ResponseFormat::Browser
+        );
+        // the most specific range wins over broader ones
+        assert_eq!(select("text/*, text/html;q=0, application/json;q=0.5"), ResponseFormat::Text);
+        assert_eq!(select("*/*, application/json;q=0"), ResponseFormat::Browser);
+        // malformed ranges are skipped
+        assert_eq!(select("text/plain;q=2, application/json"), ResponseFormat::Json);
+        // tabular formats are picked by name, never by a wildcard
+        assert_eq!(select("text/csv, */*;q=0.5"), ResponseFormat::Csv);
+        assert_eq!(select("text/markdown, application/yaml"), ResponseFormat::Yaml);
+        assert_eq!(select("text/*"), ResponseFormat::Browser);
+    }
+
+    #[test]
+    fn breaks_ties_with_the_user_agent() {
+        assert_eq!(select(None, || true), Ok(ResponseFormat::Text));
+        assert_eq!(select(Some("*/*"), || true), Ok(ResponseFormat::Text));
+        assert_eq!(select(None, || false), Ok(ResponseFormat::Browser));
+        assert_eq!(
+            select(Some("application/json, text/plain"), || false),
+            Ok(ResponseFormat::Json)
+        );
+        assert_eq!(select(Some("text/plain;q=2"), || true), Ok(ResponseFormat::Text));
+    }
+
+    #[test]
+    fn rejects_unsupported_types() {
+        for accept in ["image/png", "image/*, application/json;q=0", "*/*;q=0"] {
+            for term in [true, false] {
+                assert!(
+                    matches!(select(Some(accept), || term), Err(ApiError::NotAcceptable(_))),
+                    "{accept}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn overrides_with_query_and_extension() {
+        let get_override = |uri: &str| get_override(&uri.parse().unwrap());
+
+        assert_eq!(get_override("/"), Ok(None));
+        assert_eq!(get_override("/?format=json&oshi=furi"), Ok(Some(ResponseFormat::Json)));
+        assert_eq!(get_override("/index.txt"), Ok(Some(ResponseFormat::Text)));
+        assert_eq!(get_override("/list.json"), Ok(Some(ResponseFormat::Json)));
+        assert_eq!(get_override("/index.json?format=text"), Ok(Some(ResponseFormat::Text)));
+        assert_eq!(get_override("/list.yml"), Ok(Some(ResponseFormat::Yaml)));
+        assert_eq!(get_override("/?format=md"), Ok(Some(ResponseFormat::Markdown)));
+        assert!(matches!(get_override("/?format=xml"), Err(ApiError::BadRequest(_))));
+    }
+
+    #[test]
+    fn matches_terminal_user_agents() {
+        let patterns: Vec<String> =
+            ["curl", "wget", "httpie", "powershell", "xh"].map(String::from).to_vec();
+        let is_term = |ua: &str| is_term(Some(&HeaderValue::from_str(ua).unwrap()), &patterns);
+
+        assert!(is_term("curl/8.5.0"));
+        assert!(is_term("Wget/1.21.4"));
+        assert!(is_term("HTTPie/3.2.2"));
+        assert!(is_term("xh/0.22.0"));
+        assert!(is_term(
+            "Mozilla/5.0 (Windows NT; Windows NT 10.0; en-US) WindowsPowerShell/5.1.22621.2506"
+        ));
+        assert!(!is_term(
+            "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/130.0.0.0 Safari/537.36"
+        ));
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::{Result, anyhow};
+use std::{
+    collections::BTreeMap,
+    sync::{LazyLock, RwLock},
+};
+
+use crate::{
+    config::CONFIG,
+    platform::Platforms,
+    sqlite, twitch,
+    youtube::channels::{self, ChannelRef},
+};
+
+#[derive(Debug, Clone)]
+pub struct OshiChannel {
+    pub id: String,
+    pub platform: Platforms,
+}
+
+#[derive(Debug, Clone)]
+pub struct Talent {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub channels: Vec<OshiChannel>,
+}
+
+impl Talent {
+    /**
+     * Whether the name or one of the aliases matches, ignoring case
+     */
+    pub fn matches(&self, alias: &str) -> bool {
+        let alias = alias.trim().to_lowercase();
+        self.names().any(|name| name.to_lowercase() == alias)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.name).chain(&self.aliases)
+    }
+
+    pub fn channel_ids(&self) -> Vec<String> {
+        self.channels.iter().map(|channel| channel.id.clone()).collect()
+    }
+}
+
+/// Talents with their channels, resolved from the configured channel references
+static TALENTS: LazyLock<RwLock<Vec<Talent>>> = LazyLock::new(|| RwLock::new(Vec::new()));
+
+/**
+ * Resolves the channels of every configured talent,
+ * reporting the ones that can't be resolved instead of failing
+ */
+pub async fn init_oshi() {
+    let mut talents = Vec::new();
+
+    for talent in &CONFIG.talents {
+        let mut channels = Vec::new();
+
+        for input in &talent.channels {
+            match resolve_channel(input).await {
+                Ok(channel) => {
+                    tracing::debug!("resolved {} ({}) to {}", talent.name, input, channel.id);
+                    channels.push(channel);
+                }
+                Err(e) => tracing::error!("failed to resolve {} ({}): {}", talent.name, input, e),
+            }
+        }
+
+        if channels.is_empty() {
+            tracing::error!("no channels resolved for {}", talent.name);
+            continue;
+        }
+
+        talents.push(Talent {
+            name: talent.name.clone(),
+            aliases: talent.aliases.clone(),
+            channels,
+        });
+    }
+
+    talents.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut groups = BTreeMap::new();
+
+    for (group, members) in &CONFIG.groups {
+        let channel_ids = members
+            .iter()
+            .filter_map(|member| {
+                let talent = talents.iter().find(|talent| talent.matches(member));
+                if talent.is_none() {
+                    tracing::warn!("unknown talent {} in group {}", member, group);
+                }
+                talent
+            })
+            .flat_map(Talent::channel_ids)
+            .collect();
+
+        groups.insert(group.clone(), channel_ids);
+    }
+
+    if let Err(e) = sqlite::replace_db_groups(&groups) {
+        tracing::error!("failed to store groups: {}", e);
+    }
+
+    *TALENTS.write().unwrap() = talents;
+}
+
+/**
+ * Resolves a YouTube id, handle or url, or a Twitch login or url
+ */
+pub async fn resolve_channel(input: &str) -> Result<OshiChannel> {
+    if let Some(login) = twitch::parse_channel_login(input) {
+        let key = format!("twitch:{login}");
+        if let Some(channel_id) = sqlite::get_db_channel_ref(&key)? {
+            return Ok(OshiChannel { id: channel_id, platform: Platforms::Twitch });
+        }
+
+        let client = twitch::TWITCH_CLIENT.as_ref().ok_or(anyhow!("twitch is not configured"))?;
+        let channel = client.get_channel_by_login(&login).await?;
+        sqlite::upsert_db_channel_ref(&key, &channel.id)?;
+
+        return Ok(OshiChannel { id: channel.id, platform: Platforms::Twitch });
+    }
+
+    let Some(channel_ref) = channels::parse_channel_ref(input) else {
+        return Err(anyhow!("invalid channel: {input}"));
+    };
+
+    let id = resolve_channel_id(&channel_ref).await?;
+
+    Ok(OshiChannel { id, platform: Platforms::Youtube })
+}
+
+pub async fn resolve_channel_id(channel_ref: &ChannelRef) -> Result<String> {
+    if let ChannelRef::Id(id) = channel_ref {
+        return Ok(id.clone());
+    }
+
+    let key = channel_ref.cache_key();
+    if let Some(channel_id) = sqlite::get_db_channel_ref(&key)? {
+        return Ok(channel_id);
+    }
+
+    let channel = channels::resolve_channel(channel_ref).await?;
+    sqlite::upsert_db_channel_ref(&key, &channel.id)?;
+
+    Ok(channel.id)
+}
+
+pub fn get_talent(alias: &str) -> Option<Talent> {
+    TALENTS.read().unwrap().iter().find(|talent| talent.matches(alias)).cloned()
+}
+
+pub fn get_talents() -> Vec<Talent> {
+    TALENTS.read().unwrap().clone()
+}
+
+/**
+ * Finds the talent the user most likely meant when an alias doesn't match
+ */
+pub fn suggest_alias(alias: &str) -> Option<String> {
+    let talents = TALENTS.read().unwrap();
+
+    closest_name(alias, talents.iter().flat_map(Talent::names)).map(str::to_string)
+}
+
+fn closest_name<'a>(input: &str, names: impl Iterator<Item = &'a String>) -> Option<&'a str> {
+    let input = input.trim().to_lowercase();
+    // allow roughly one typo every 3 characters
+    let max_distance = (input.chars().count() / 3).max(1);
+
+    names
+        .map(|name| (name, edit_distance(&input, &name.to_lowercase())))
+        .filter(|(_, distance)| *distance <= max_distance)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(name, _)| name.as_str())
+}
+
+/**
+ * Levenshtein distance between two strings, counted in chars
+ */
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_aliases_ignoring_case() {
+        let talent = Talent {
+            name: "Furi".to_string(),
+            aliases: vec!["Ärzte".to_string(), "ФУРИ".to_string(), "ふりちゃん".to_string()],
+            channels: vec![],
+        };
+
+        assert!(talent.matches(" furi "));
+        assert!(talent.matches("ärzte"));
+        assert!(talent.matches("фури"));
+        assert!(talent.matches("ふりちゃん"));
+        assert!(!talent.matches("fur"));
+        assert!(!talent.matches("ふり"));
+    }
+
+    #[test]
+    fn edit_distance_counts_chars() {
+        assert_eq!(edit_distance("furi", "furi"), 0);
+        assert_eq!(edit_distance("fury", "furi"), 1);
+        assert_eq!(edit_distance("phsih", "phish"), 2);
+        assert_eq!(edit_distance("ふり", "ふりちゃん"), 3);
+    }
+
+    #[test]
+    fn suggests_closest_name() {
+        let names: Vec<String> = ["furi", "furina", "phish", "mono"].map(String::from).to_vec();
+
+        assert_eq!(closest_name("Fury", names.iter()), Some("furi"));
+        assert_eq!(closest_name("phsh", names.iter()), Some("phish"));
+        assert_eq!(closest_name("raki", names.iter()), None);
+    }
+}
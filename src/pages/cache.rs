@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant, SystemTime},
+};
+
+use super::{PageContext, Pages, Rendered};
+
+#[derive(Hash, Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+    Tsv,
+    Yaml,
+    Markdown,
+}
+
+impl Format {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Text => "text/plain",
+            Format::Json => "application/json",
+            Format::Csv => "text/csv",
+            Format::Tsv => "text/tab-separated-values",
+            Format::Yaml => "application/yaml",
+            Format::Markdown => "text/markdown",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Json => write!(f, "json"),
+            Format::Csv => write!(f, "csv"),
+            Format::Tsv => write!(f, "tsv"),
+            Format::Yaml => write!(f, "yaml"),
+            Format::Markdown => write!(f, "markdown"),
+        }
+    }
+}
+
+/// A rendered page is cached per format and normalised query parameters
+#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+pub struct CacheKey {
+    pub page: Pages,
+    pub format: Format,
+    pub ctx: PageContext,
+}
+
+struct Entry {
+    rendered: Rendered,
+    /// Generation of the page the entry was rendered at
+    generation: u64,
+    expires: Instant,
+}
+
+/**
+ * Rendered pages, dropped once their time to live is over or their page is invalidated.
+ * Invalidating bumps the generation of the page, so renders started before then aren't kept
+ */
+pub struct PageCache {
+    entries: HashMap<CacheKey, Entry>,
+    generations: HashMap<Pages, u64>,
+    /// When each page was last invalidated
+    modified: HashMap<Pages, SystemTime>,
+    started: SystemTime,
+    ttl: Duration,
+    max_entries: usize,
+}
+
+impl PageCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        PageCache {
+            entries: HashMap::new(),
+            generations: HashMap::new(),
+            modified: HashMap::new(),
+            started: SystemTime::now(),
+            ttl,
+            max_entries,
+        }
+    }
+
+    pub fn generation(&self, page: Pages) -> u64 {
+        self.generations.get(&page).copied().unwrap_or_default()
+    }
+
+    /**
+     * Time of the latest write shown by the page, or startup when there was none since
+     */
+    pub fn modified(&self, page: Pages) -> SystemTime {
+        self.modified.get(&page).copied().unwrap_or(self.started)
+    }
+
+    pub fn get(&self, key: &CacheKey, now: Instant) -> Option<Rendered> {
+        self.entries
+            .get(key)
+            .filter(|entry| entry.expires > now && entry.generation == self.generation(key.page))
+            .map(|entry| entry.rendered.clone())
+    }
+
+    /**
+     * Stores a page rendered at `generation`, unless it was invalidated in the meantime
+     */
+    pub fn insert(&mut self, key: CacheKey, rendered: Rendered, generation: u64, now: Instant) {
+        if generation != self.generation(key.page) {
+            return;
+        }
+
+        // filtered pages would otherwise pile up
+        self.entries.retain(|_, entry| entry.expires > now);
+
+        // past the cap, the oldest page makes room
+        if self.entries.len() >= self.max_entries && !self.entries.contains_key(&key) {
+            let oldest = self.entries.iter().min_by_key(|(_, entry)| entry.expires);
+            if let Some(oldest) = oldest.map(|(key, _)| key.clone()) {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        if self.max_entries > 0 {
+            self.entries.insert(key, Entry { rendered, generation, expires: now + self.ttl });
+        }
+    }
+
+    pub fn invalidate(&mut self, page: Pages) {
+        *self.generations.entry(page).or_default() += 1;
+        self.modified.insert(page, SystemTime::now());
+        self.entries.retain(|key, _| key.page != page);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGES: [Pages; 3] = [Pages::Root, Pages::List, Pages::Groups];
+    const FORMATS: [Format; 6] =
+        [Format::Text, Format::Json, Format::Csv, Format::Tsv, Format::Yaml, Format::Markdown];
+
+    fn key(page: Pages, format: Format) -> CacheKey {
+        CacheKey { page, format, ctx: PageContext::default() }
+    }
+
+    fn rendered(page: Pages, format: Format) -> Rendered {
+        format!("{page:?} {format:?}").into()
+    }
+
+    fn filled(now: Instant) -> PageCache {
+        let mut cache = PageCache::new(Duration::from_secs(60), 100);
+
+        for page in PAGES {
+            for format in FORMATS {
+                let generation = cache.generation(page);
+                cache.insert(key(page, format), rendered(page, format), generation, now);
+            }
+        }
+
+        cache
+    }
+
+    #[test]
+    fn keeps_pages_and_formats_apart() {
+        let now = Instant::now();
+        let cache = filled(now);
+
+        for page in PAGES {
+            for format in FORMATS {
+                assert_eq!(
+                    cache.get(&key(page, format), now).map(|r| r.body),
+                    Some(rendered(page, format).body)
+                );
+            }
+        }
+
+        let filtered = CacheKey {
+            ctx: PageContext { oshi: vec!["furi".to_string()], ..Default::default() },
+            ..key(Pages::Root, Format::Text)
+        };
+        assert!(cache.get(&filtered, now).is_none());
+    }
+
+    #[test]
+    fn expires_entries() {
+        let now = Instant::now();
+        let cache = filled(now);
+
+        for page in PAGES {
+            for format in FORMATS {
+                assert!(cache.get(&key(page, format), now + Duration::from_secs(59)).is_some());
+                assert!(cache.get(&key(page, format), now + Duration::from_secs(60)).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn invalidates_a_single_page() {
+        let now = Instant::now();
+
+        for invalidated in PAGES {
+            let mut cache = filled(now);
+            cache.invalidate(invalidated);
+
+            for page in PAGES {
+                for format in FORMATS {
+                    let hit = cache.get(&key(page, format), now).is_some();
+                    assert_eq!(hit, page != invalidated, "{page:?} {format:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn caps_entries() {
+        let now = Instant::now();
+        let mut cache = PageCache::new(Duration::from_secs(60), 3);
+
+        let limited = |i: u64| CacheKey {
+            ctx: PageContext { limit: i as usize + 1, ..Default::default() },
+            ..key(Pages::Root, Format::Json)
+        };
+
+        for i in 0..5 {
+            let at = now + Duration::from_secs(i);
+            cache.insert(limited(i), rendered(Pages::Root, Format::Json), 0, at);
+        }
+
+        let later = now + Duration::from_secs(5);
+        let hits: Vec<bool> = (0..5).map(|i| cache.get(&limited(i), later).is_some()).collect();
+        assert_eq!(hits, [false, false, true, true, true]);
+
+        // replacing a page doesn't evict another one
+        cache.insert(limited(4), rendered(Pages::Root, Format::Json), 0, later);
+        assert!(cache.get(&limited(2), later).is_some());
+    }
+
+    #[test]
+    fn drops_renders_started_before_invalidation() {
+        let now = Instant::now();
+        let mut cache = PageCache::new(Duration::from_secs(60), 100);
+
+        for page in PAGES {
+            for format in FORMATS {
+                let generation = cache.generation(page);
+                cache.invalidate(page);
+                cache.insert(key(page, format), rendered(page, format), generation, now);
+
+                assert!(cache.get(&key(page, format), now).is_none(), "{page:?} {format:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::{
+    api::DbChannel,
+    colors::{Colorize, Role},
+    oshi::{self, Talent},
+    pages::PageContext,
+    platform::Platforms,
+    sqlite,
+};
+
+use super::{
+    Render, Rendered,
+    table::{Cell, Table},
+};
+
+/// Columns of the tabular formats, a row per member
+const COLUMNS: [&str; 7] = ["group", "talent", "channel", "handle", "platform", "id", "url"];
+
+#[derive(Serialize)]
+struct GroupMemberJson {
+    talent: String,
+    id: String,
+    name: String,
+    url: String,
+    platform: Platforms,
+}
+
+#[derive(Serialize)]
+struct GroupJson {
+    name: String,
+    members: Vec<GroupMemberJson>,
+}
+
+#[derive(Serialize)]
+struct GroupsResponse {
+    groups: Vec<GroupJson>,
+}
+
+pub struct Page {}
+
+impl Render for Page {
+    async fn render_text(&self, ctx: PageContext) -> Result<Rendered> {
+        let groups = get_groups()?;
+
+        if groups.is_empty() {
+            return Ok(ctx.locale.messages().no_groups.to_string().into());
+        }
+
+        Ok(format_groups_text(&groups, &ctx).into())
+    }
+
+    async fn render_json(&self, _ctx: PageContext) -> Result<Rendered> {
+        Ok(serde_json::to_string(&to_response(get_groups()?))?.into())
+    }
+
+    async fn render_table(&self, _ctx: PageContext) -> Result<Option<Table>> {
+        Ok(Some(to_table(get_groups()?)))
+    }
+}
+
+fn format_groups_text(groups: &[Group], ctx: &PageContext) -> String {
+    let group_list: Vec<String> = groups
+        .iter()
+        .map(|(name, members)| {
+            let mut entry = name.clone();
+
+            for (talent, channel) in members {
+                entry.push_str(&format!(
+                    "\n  {}: {} {}",
+                    talent,
+                    channel.name,
+                    &channel.platform.channel_url(channel).paint(Role::Link, ctx.text.color)
+                ));
+            }
+
+            entry
+        })
+        .collect();
+
+    group_list.join("\n")
+}
+
+fn to_response(groups: Vec<Group>) -> GroupsResponse {
+    let groups = groups
+        .into_iter()
+        .map(|(name, members)| GroupJson {
+            name,
+            members: members
+                .into_iter()
+                .map(|(talent, channel)| GroupMemberJson {
+                    talent,
+                    id: channel.id.clone(),
+                    name: channel.name.clone(),
+                    url: channel.platform.channel_url(&channel),
+                    platform: channel.platform,
+                })
+                .collect(),
+        })
+        .collect();
+
+    GroupsResponse { groups }
+}
+
+fn to_table(groups: Vec<Group>) -> Table {
+    let rows = groups
+        .into_iter()
+        .flat_map(|(name, members)| {
+            members.into_iter().map(move |(talent, channel)| {
+                vec![
+                    Cell::from(name.clone()),
+                    talent.into(),
+                    channel.name.clone().into(),
+                    channel.handle.clone().into(),
+                    channel.platform.to_string().into(),
+                    channel.id.clone().into(),
+                    channel.platform.channel_url(&channel).into(),
+                ]
+            })
+        })
+        .collect();
+
+    Table { columns: COLUMNS.to_vec(), rows, next: None }
+}
+
+/// A group name with the talent and channel of each member
+type Group = (String, Vec<(String, DbChannel)>);
+
+/**
+ * Groups with the talent and channel of each member
+ */
+fn get_groups() -> Result<Vec<Group>> {
+    Ok(to_groups(sqlite::get_db_groups()?, &sqlite::get_db_channels()?, &oshi::get_talents()))
+}
+
+fn to_groups(
+    groups: BTreeMap<String, Vec<String>>,
+    channels: &[DbChannel],
+    talents: &[Talent],
+) -> Vec<Group> {
+    groups
+        .into_iter()
+        .map(|(name, channel_ids)| {
+            let members: Vec<(String, DbChannel)> = talents
+                .iter()
+                .flat_map(|talent| talent.channels.iter().map(|oshi| (&talent.name, oshi)))
+                .filter(|(_, oshi)| channel_ids.contains(&oshi.id))
+                .filter_map(|(talent, oshi)| {
+                    let channel = channels.iter().find(|c| c.id == oshi.id)?;
+                    Some((talent.clone(), channel.clone()))
+                })
+                .collect();
+
+            (name, members)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::{colors::ColorMode, oshi::OshiChannel, text::TextOptions};
+
+    fn groups() -> Vec<Group> {
+        let oshi = |id: &str, platform| OshiChannel { id: id.to_string(), platform };
+        let talents = [
+            Talent {
+                name: "furi".to_string(),
+                aliases: vec![],
+                channels: vec![oshi("UCfuri", Platforms::Youtube), oshi("1001", Platforms::Twitch)],
+            },
+            Talent {
+                name: "mono".to_string(),
+                aliases: vec![],
+                channels: vec![oshi("UCmono", Platforms::Youtube)],
+            },
+        ];
+        let channels = [
+            DbChannel {
+                id: "UCfuri".to_string(),
+                name: "Furi Ch.".to_string(),
+                handle: Some("@furi".to_string()),
+                ..Default::default()
+            },
+            DbChannel {
+                id: "1001".to_string(),
+                name: "Furi".to_string(),
+                handle: Some("furi".to_string()),
+                platform: Platforms::Twitch,
+                ..Default::default()
+            },
+            DbChannel { id: "UCmono".to_string(), name: "Mono".to_string(), ..Default::default() },
+        ];
+        let groups = BTreeMap::from([
+            ("gen2".to_string(), vec!["UCmono".to_string()]),
+            // channels that aren't stored are left out
+            (
+                "unit".to_string(),
+                vec!["UCfuri".to_string(), "1001".to_string(), "UCgone".to_string()],
+            ),
+        ]);
+
+        to_groups(groups, &channels, &talents)
+    }
+
+    #[test]
+    fn renders_groups_as_text() {
+        let ctx = PageContext {
+            text: TextOptions { color: ColorMode::Never, ..Default::default() },
+            ..Default::default()
+        };
+
+        let expected = [
+            "gen2",
+            "  mono: Mono https://www.youtube.com/channel/UCmono",
+            "unit",
+            "  furi: Furi Ch. https://www.youtube.com/channel/UCfuri",
+            "  furi: Furi https://www.twitch.tv/furi",
+        ];
+        assert_eq!(format_groups_text(&groups(), &ctx), expected.join("\n"));
+    }
+
+    #[test]
+    fn renders_groups_as_json() {
+        let json: Value = serde_json::to_value(to_response(groups())).unwrap();
+
+        assert_eq!(json["groups"][0]["name"], "gen2");
+        assert_eq!(
+            json["groups"][1],
+            json!({
+                "name": "unit",
+                "members": [
+                    {
+                        "talent": "furi",
+                        "id": "UCfuri",
+                        "name": "Furi Ch.",
+                        "url": "https://www.youtube.com/channel/UCfuri",
+                        "platform": "youtube"
+                    },
+                    {
+                        "talent": "furi",
+                        "id": "1001",
+                        "name": "Furi",
+                        "url": "https://www.twitch.tv/furi",
+                        "platform": "twitch"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn renders_groups_as_rows() {
+        let table = to_table(groups());
+
+        assert_eq!(table.columns, COLUMNS);
+        let ids: Vec<(String, String)> =
+            table.rows.iter().map(|row| (row[0].to_string(), row[5].to_string())).collect();
+        assert_eq!(
+            ids,
+            [("gen2", "UCmono"), ("unit", "UCfuri"), ("unit", "1001")]
+                .map(|(group, id)| (group.to_string(), id.to_string()))
+        );
+    }
+}
@@ -1,9 +1,44 @@
 use anyhow::Result;
+use chrono_tz::Tz;
 use serde::Serialize;
+use std::collections::BTreeMap;
 
-use crate::{api::DbVideo, colors::Colorize, pages::PageContext, sqlite, time};
+use crate::{
+    api::VideoKind,
+    colors::{Colorize, Role},
+    events::Event,
+    pages::PageContext,
+    platform::Platforms,
+    query,
+    text::{self, Layout},
+    time,
+};
 
-use super::Render;
+use super::{
+    Render, Rendered,
+    table::{Cell, Table},
+};
+
+/// Columns of the tabular formats, named like the fields of [`VideoJson`]
+const COLUMNS: [&str; 17] = [
+    "status",
+    "kind",
+    "collab",
+    "title",
+    "url",
+    "platform",
+    "id",
+    "channel",
+    "channel_id",
+    "scheduled_time",
+    "scheduled_time_local",
+    "start_time",
+    "start_time_local",
+    "seconds_until_start",
+    "concurrent_viewers",
+    "duration",
+    "premiere",
+];
 
 #[derive(Serialize)]
 struct VideoChannel {
@@ -11,129 +46,249 @@ struct VideoChannel {
     id: String,
 }
 
+#[derive(Serialize)]
+struct VideoUrl {
+    platform: Platforms,
+    url: String,
+}
+
 #[derive(Serialize)]
 struct VideoJson {
     status: String,
+    kind: VideoKind,
+    collab: bool,
     title: String,
     url: String,
+    /// Every channel the stream is simulcast on, starting with `url`
+    urls: Vec<VideoUrl>,
+    platform: Platforms,
     id: String,
     channel: VideoChannel,
+    description: Option<String>,
+    thumbnails: BTreeMap<String, String>,
+    live_broadcast_content: String,
+    concurrent_viewers: Option<i64>,
+    duration: Option<i64>,
+    premiere: bool,
+    /// RFC3339 in UTC
+    scheduled_time: String,
+    /// RFC3339 in the requested time zone
+    scheduled_time_local: String,
+    start_time: Option<String>,
+    start_time_local: Option<String>,
+    /// Until `scheduled_time` when the page was rendered, negative once it has passed
+    seconds_until_start: Option<i64>,
 }
 
 #[derive(Serialize)]
 struct VideosResponse {
+    /// Zone of the local times
+    timezone: String,
     videos: Vec<VideoJson>,
+    /// Link to the following videos, if there are more
+    next: Option<String>,
 }
 
 pub struct Page {}
 
 impl Render for Page {
-    async fn render_text(&self, ctx: PageContext) -> Result<String> {
-        if ctx.channel_id.as_deref() == Some("invalid") {
-            return Ok("that channel is not tracked".to_string());
+    async fn render_text(&self, ctx: PageContext) -> Result<Rendered> {
+        let page = query::get_video_page(&ctx)?;
+
+        if page.events.is_empty() {
+            return Ok(ctx.locale.messages().no_upcoming_streams.to_string().into());
         }
 
-        let videos = match &ctx.channel_id {
-            Some(channel_id) if channel_id == "all" => sqlite::get_db_upcoming_videos(&None)
-                .unwrap_or_else(|e| {
-                    tracing::error!("failed to fetch all upcoming videos: {}", e);
-                    Vec::new()
-                }),
-            Some(channel_id) => sqlite::get_db_upcoming_videos(&Some(channel_id.clone()))
-                .unwrap_or_else(|err| {
-                    tracing::error!(
-                        "failed to fetch upcoming videos for channel {}: {}",
-                        channel_id,
-                        err
-                    );
-                    Vec::new()
-                }),
-            None => sqlite::get_db_upcoming_videos(&None).unwrap_or_else(|e| {
-                tracing::error!("failed to fetch all upcoming videos: {}", e);
-                Vec::new()
-            }),
-        };
+        let next = page.next.as_ref().map(|next| query::page_link("/", next));
 
-        if videos.is_empty() {
-            return Ok("no upcoming streams".to_string());
-        }
+        let mut video_list: Vec<String> =
+            page.events.iter().map(|event| format_video_text(event, &ctx)).collect();
 
-        let video_list: Vec<String> = videos //
-            .iter()
-            .map(format_video_text)
-            .collect();
+        if let Some(next) = &next {
+            video_list
+                .push(ctx.locale.more(page.remaining, &next.paint(Role::Link, ctx.text.color)));
+        }
 
-        Ok(video_list.join("\n"))
+        Ok(Rendered { next, ..video_list.join("\n").into() })
     }
 
-    async fn render_json(&self, ctx: PageContext) -> Result<String> {
-        if ctx.channel_id.as_deref() == Some("invalid") {
-            return Ok(serde_json::to_string(
-                &serde_json::json!({"error": "that channel is not tracked"}),
-            )?);
-        }
+    async fn render_json(&self, ctx: PageContext) -> Result<Rendered> {
+        let page = query::get_video_page(&ctx)?;
 
-        let videos = match &ctx.channel_id {
-            Some(channel_id) if channel_id == "all" => sqlite::get_db_upcoming_videos(&None)
-                .unwrap_or_else(|e| {
-                    tracing::error!("failed to fetch all upcoming videos: {}", e);
-                    Vec::new()
-                }),
-            Some(channel_id) => sqlite::get_db_upcoming_videos(&Some(channel_id.clone()))
-                .unwrap_or_else(|err| {
-                    tracing::error!(
-                        "failed to fetch upcoming videos for channel {}: {}",
-                        channel_id,
-                        err
-                    );
-                    Vec::new()
-                }),
-            None => sqlite::get_db_upcoming_videos(&None).unwrap_or_else(|e| {
-                tracing::error!("failed to fetch all upcoming videos: {}", e);
-                Vec::new()
-            }),
-        };
+        let next = page.next.as_ref().map(|next| query::page_link("/", next));
+
+        let video_list: Vec<VideoJson> =
+            page.events.iter().map(|event| format_video_json(event, ctx.tz)).collect();
+
+        let body = serde_json::to_string(&VideosResponse {
+            timezone: ctx.tz.name().to_string(),
+            videos: video_list,
+            next: next.clone(),
+        })?;
 
-        let video_list: Vec<VideoJson> = videos //
+        Ok(Rendered { next, ..body.into() })
+    }
+
+    async fn render_table(&self, ctx: PageContext) -> Result<Option<Table>> {
+        let page = query::get_video_page(&ctx)?;
+
+        let rows = page
+            .events
             .iter()
-            .map(format_video_json)
+            .map(|event| {
+                let video = format_video_json(event, ctx.tz);
+
+                vec![
+                    Cell::from(video.status),
+                    video.kind.to_string().into(),
+                    video.collab.into(),
+                    video.title.into(),
+                    video.url.into(),
+                    video.platform.to_string().into(),
+                    video.id.into(),
+                    video.channel.name.into(),
+                    video.channel.id.into(),
+                    video.scheduled_time.into(),
+                    video.scheduled_time_local.into(),
+                    video.start_time.into(),
+                    video.start_time_local.into(),
+                    video.seconds_until_start.into(),
+                    video.concurrent_viewers.into(),
+                    video.duration.into(),
+                    video.premiere.into(),
+                ]
+            })
             .collect();
 
-        Ok(serde_json::to_string(&VideosResponse { videos: video_list })?)
+        Ok(Some(Table {
+            columns: COLUMNS.to_vec(),
+            rows,
+            next: page.next.as_ref().map(|next| query::page_link("/", next)),
+        }))
     }
 }
 
-fn format_video_text(video: &DbVideo) -> String {
+fn format_video_text(event: &Event, ctx: &PageContext) -> String {
+    let video = event.primary();
+    let messages = ctx.locale.messages();
+    let color = ctx.text.color;
+
     let status: String = match video.end_time.is_some() {
-        true => "[ended]".bright_purple(),
+        true => format!("[{}]", messages.ended).paint(Role::Ended, color),
         false => match video.start_time.is_some() {
-            true => "[live]".bright_red(),
-            false => "[upcoming]".bright_yellow(),
+            true => format!("[{}]", messages.live).paint(Role::Live, color),
+            false => format!("[{}]", messages.upcoming).paint(Role::Upcoming, color),
         },
     };
 
-    let title = &video.title.green();
-    let url = &format!("https://www.youtube.com/watch?v={}", video.id).light_blue();
+    let status = match video.kind {
+        VideoKind::Live => status,
+        kind => format!("{status} ({kind})"),
+    };
+
+    let status = match video.collab {
+        true => format!("{status} [{}]", messages.collab),
+        false => status,
+    };
 
-    let mut entry = match &video.channel_name {
-        Some(name) => format!("{status} {title}\nchannel:   {name}\nurl:       {url}\n",),
-        None => format!("{status} {title}\nurl:       {url}\n"),
+    // times that can't be parsed come without the time left
+    let with_diff = |date: String, diff: String| match diff.is_empty() {
+        true => date,
+        false => format!("{date} ({diff})"),
     };
 
-    if let Some(start_time) = &video.start_time {
-        let (date, diff) = time::humanize(start_time);
+    let (label, time) = match &video.start_time {
+        Some(start_time) => {
+            let (date, diff) = time::humanize(start_time, ctx.tz, ctx.locale, ctx.precision);
+            (messages.started, with_diff(date, diff))
+        }
+        None => {
+            let (date, diff) =
+                time::humanize(&video.scheduled_time, ctx.tz, ctx.locale, ctx.precision);
+
+            // late streams keep their waiting room open until they go live
+            let diff = match time::seconds_until(&video.scheduled_time) {
+                Some(seconds) if seconds <= 0 => messages.starting.to_string(),
+                _ => diff,
+            };
 
-        entry.push_str(&format!("started:   {}\n", &format!("{date} UTC ({diff})")));
-    } else {
-        let (date, diff) = time::humanize(&video.scheduled_time);
+            (messages.scheduled, with_diff(date, diff))
+        }
+    };
+
+    if ctx.text.layout == Layout::Compact {
+        let mut line = format!("{status} {time} ");
 
-        entry.push_str(&format!("scheduled: {}\n", &format!("{date} UTC ({diff})")));
+        if let Some(name) = &video.channel_name {
+            line.push_str(&format!("{name}: "));
+        }
+
+        // the url goes last, so titles are cut before it
+        let url = video.platform.video_url(video);
+        let title = match ctx.text.width {
+            Some(width) => {
+                let used = text::display_width(&line) + 1 + text::display_width(&url);
+                text::truncate(&video.title, text::title_width(width, used))
+            }
+            None => video.title.clone(),
+        };
+
+        return format!(
+            "{line}{} {}",
+            title.paint(Role::Title, color),
+            url.paint(Role::Link, color)
+        );
+    }
+
+    // wrapped lines of the title are lined up after the status
+    let indent = text::display_width(&status) + 1;
+    let title = match ctx.text.width {
+        Some(width) => text::wrap(&video.title, text::title_width(width, indent))
+            .iter()
+            .map(|line| line.paint(Role::Title, color))
+            .collect::<Vec<_>>()
+            .join(&format!("\n{}", " ".repeat(indent))),
+        None => video.title.paint(Role::Title, color),
+    };
+    let url = &video.platform.video_url(video).paint(Role::Link, color);
+
+    let mut entry = format!("{status} {title}\n");
+
+    if let Some(name) = &video.channel_name {
+        entry.push_str(&field(messages.channel, name));
+    }
+
+    entry.push_str(&field(messages.url, url));
+
+    for simulcast in event.simulcasts() {
+        let url = simulcast.platform.video_url(simulcast).paint(Role::Link, color);
+        entry.push_str(&field(messages.url, &url));
+    }
+
+    entry.push_str(&field(label, &time));
+
+    if video.start_time.is_some()
+        && let Some(viewers) = video.concurrent_viewers
+    {
+        entry.push_str(&field(messages.viewers, &viewers.to_string()));
     }
 
     entry
 }
 
-fn format_video_json(video: &DbVideo) -> VideoJson {
+/**
+ * A labelled line of an entry, values lined up after the longest label
+ */
+fn field(label: &str, value: &str) -> String {
+    let label = format!("{label}:");
+    let padding = " ".repeat(11usize.saturating_sub(text::display_width(&label)));
+
+    format!("{label}{padding}{value}\n")
+}
+
+fn format_video_json(event: &Event, tz: Tz) -> VideoJson {
+    let video = event.primary();
+
     let status = if video.end_time.is_some() {
         "ended"
     } else if video.start_time.is_some() {
@@ -144,12 +299,64 @@ fn format_video_json(video: &DbVideo) -> VideoJson {
 
     VideoJson {
         status: status.to_string(),
+        kind: video.kind,
+        collab: video.collab,
         title: video.title.clone(),
-        url: format!("https://www.youtube.com/watch?v={}", video.id),
+        url: video.platform.video_url(video),
+        urls: event
+            .videos
+            .iter()
+            .map(|v| VideoUrl { platform: v.platform, url: v.platform.video_url(v) })
+            .collect(),
+        platform: video.platform,
         id: video.id.clone(),
         channel: VideoChannel {
             name: video.channel_name.clone().unwrap_or_default(),
             id: video.channel_id.clone(),
         },
+        description: video.description.clone(),
+        thumbnails: video.thumbnails.clone(),
+        live_broadcast_content: video.live_broadcast_content.clone(),
+        concurrent_viewers: video.concurrent_viewers,
+        duration: video.duration,
+        premiere: video.premiere,
+        scheduled_time: video.scheduled_time.clone(),
+        scheduled_time_local: time::localize(&video.scheduled_time, tz),
+        start_time: video.start_time.clone(),
+        start_time_local: video.start_time.as_deref().map(|time| time::localize(time, tz)),
+        seconds_until_start: time::seconds_until(&video.scheduled_time),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::DbVideo, colors::ColorMode, text::TextOptions};
+
+    #[test]
+    fn keeps_urls_in_compact_layout() {
+        let event = Event {
+            videos: vec![DbVideo {
+                id: "dQw4w9WgXcQ".to_string(),
+                channel_name: Some("Furi".to_string()),
+                title: "Karaoke night with every song requested in chat until morning".to_string(),
+                scheduled_time: "2026-01-01T12:00:00Z".to_string(),
+                ..Default::default()
+            }],
+        };
+
+        for width in [None, Some(140), Some(110)] {
+            let ctx = PageContext {
+                text: TextOptions { color: ColorMode::Never, width, layout: Layout::Compact },
+                ..Default::default()
+            };
+            let line = format_video_text(&event, &ctx);
+
+            assert!(line.ends_with(" https://www.youtube.com/watch?v=dQw4w9WgXcQ"), "{line}");
+            assert!(!line.contains('\n'), "{line}");
+            if let Some(width) = width {
+                assert!(text::display_width(&line) <= width, "{line}");
+            }
+        }
     }
 }
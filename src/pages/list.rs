@@ -1,78 +1,273 @@
 use anyhow::Result;
 use serde::Serialize;
+use std::collections::BTreeMap;
 
-use crate::{colors::Colorize, config::CONFIG, pages::PageContext, sqlite};
+use crate::{
+    api::DbChannel,
+    colors::{Colorize, Role},
+    oshi::{self, Talent},
+    pages::PageContext,
+    platform::Platforms,
+    sqlite,
+};
 
-use super::Render;
+use super::{
+    Render, Rendered,
+    table::{Cell, Table},
+};
 
-#[derive(Serialize)]
+/// Columns of the tabular formats, a row per channel
+const COLUMNS: [&str; 9] = [
+    "talent",
+    "aliases",
+    "groups",
+    "channel",
+    "handle",
+    "platform",
+    "id",
+    "url",
+    "subscriber_count",
+];
+
+#[derive(Clone, Serialize)]
 struct ChannelJson {
     id: String,
-    alias: String,
     name: String,
     url: String,
+    platform: Platforms,
+    handle: Option<String>,
+    description: Option<String>,
+    country: Option<String>,
+    thumbnails: BTreeMap<String, String>,
+    banner: Option<String>,
+    subscriber_count: Option<i64>,
 }
 
 #[derive(Serialize)]
-struct ChannelsResponse {
+struct TalentJson {
+    name: String,
+    aliases: Vec<String>,
+    groups: Vec<String>,
     channels: Vec<ChannelJson>,
 }
 
+/// A channel of the flat list `/list` has always answered with, `alias` being its talent's name
+#[derive(Serialize)]
+struct ListedChannelJson {
+    alias: String,
+    #[serde(flatten)]
+    channel: ChannelJson,
+    groups: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ListResponse {
+    channels: Vec<ListedChannelJson>,
+    /// The same channels, grouped per talent
+    talents: Vec<TalentJson>,
+}
+
 pub struct Page {}
 
 impl Render for Page {
-    async fn render_text(&self, _ctx: PageContext) -> Result<String> {
-        let channels = sqlite::get_db_channels().unwrap_or_else(|_| {
-            tracing::error!("failed to fetch channels from db");
-            Vec::new()
-        });
-
-        if channels.is_empty() {
-            return Ok("no channels found".to_string());
+    async fn render_text(&self, ctx: PageContext) -> Result<Rendered> {
+        let messages = ctx.locale.messages();
+        let talents = get_talents()?;
+
+        if talents.is_empty() {
+            return Ok(messages.no_channels.to_string().into());
         }
 
-        let channel_list: Vec<String> = CONFIG
-            .oshi
+        let talent_list: Vec<String> = talents
             .iter()
-            .filter_map(|(alias, id)| {
-                channels.iter().find(|c| &c.id == id).map(|channel| {
-                    format!(
-                        "{}\n  name: {}\n  url:  {}\n  id:   {}",
-                        alias,
-                        channel.name,
-                        &format!("https://www.youtube.com/channel/{}", channel.id).light_blue(),
+            .map(|(talent, groups, channels)| {
+                let mut entry = talent.name.clone();
+
+                if !talent.aliases.is_empty() {
+                    entry.push_str(&format!(
+                        "\n  {:<9}{}",
+                        format!("{}:", messages.aliases),
+                        talent.aliases.join(", ")
+                    ));
+                }
+
+                if !groups.is_empty() {
+                    entry.push_str(&format!(
+                        "\n  {:<9}{}",
+                        format!("{}:", messages.groups),
+                        groups.join(", ")
+                    ));
+                }
+
+                for channel in channels {
+                    let name = match &channel.handle {
+                        Some(handle) => format!("{} ({handle})", channel.name),
+                        None => channel.name.clone(),
+                    };
+
+                    entry.push_str(&format!(
+                        "\n  - {}\n    url:  {}\n    id:   {}",
+                        name,
+                        &channel.platform.channel_url(channel).paint(Role::Link, ctx.text.color),
                         channel.id
-                    )
-                })
+                    ));
+
+                    if let Some(subscriber_count) = channel.subscriber_count {
+                        entry.push_str(&format!("\n    {}: {subscriber_count}", messages.subs));
+                    }
+                }
+
+                entry
             })
             .collect();
 
-        Ok(channel_list.join("\n"))
+        Ok(talent_list.join("\n").into())
     }
 
-    async fn render_json(&self, _ctx: PageContext) -> Result<String> {
-        let channels = sqlite::get_db_channels().unwrap_or_else(|_| {
-            tracing::error!("failed to fetch channels from db");
-            Vec::new()
-        });
+    async fn render_json(&self, _ctx: PageContext) -> Result<Rendered> {
+        Ok(serde_json::to_string(&to_response(get_talents()?))?.into())
+    }
 
-        if channels.is_empty() {
-            return Ok(serde_json::to_string(&ChannelsResponse { channels: vec![] })?);
-        }
+    async fn render_table(&self, _ctx: PageContext) -> Result<Option<Table>> {
+        let rows = get_talents()?
+            .into_iter()
+            .flat_map(|(talent, groups, channels)| {
+                channels.into_iter().map(move |channel| {
+                    let url = channel.platform.channel_url(&channel);
 
-        let channel_list: Vec<ChannelJson> = CONFIG
-            .oshi
-            .iter()
-            .filter_map(|(alias, id)| {
-                channels.iter().find(|c| &c.id == id).map(|channel| ChannelJson {
+                    vec![
+                        Cell::from(talent.name.clone()),
+                        talent.aliases.join(", ").into(),
+                        groups.join(", ").into(),
+                        channel.name.into(),
+                        channel.handle.into(),
+                        channel.platform.to_string().into(),
+                        channel.id.into(),
+                        url.into(),
+                        channel.subscriber_count.into(),
+                    ]
+                })
+            })
+            .collect();
+
+        Ok(Some(Table { columns: COLUMNS.to_vec(), rows, next: None }))
+    }
+}
+
+fn to_response(talents: Vec<TalentEntry>) -> ListResponse {
+    let talents: Vec<TalentJson> = talents
+        .into_iter()
+        .map(|(talent, groups, channels)| TalentJson {
+            name: talent.name,
+            aliases: talent.aliases,
+            groups,
+            channels: channels
+                .into_iter()
+                .map(|channel| ChannelJson {
                     id: channel.id.clone(),
-                    alias: alias.clone(),
                     name: channel.name.clone(),
-                    url: format!("https://www.youtube.com/channel/{}", channel.id),
+                    url: channel.platform.channel_url(&channel),
+                    platform: channel.platform,
+                    handle: channel.handle,
+                    description: channel.description,
+                    country: channel.country,
+                    thumbnails: channel.thumbnails,
+                    banner: channel.banner,
+                    subscriber_count: channel.subscriber_count,
                 })
+                .collect(),
+        })
+        .collect();
+
+    let channels = talents
+        .iter()
+        .flat_map(|talent| {
+            talent.channels.iter().map(|channel| ListedChannelJson {
+                alias: talent.name.clone(),
+                channel: channel.clone(),
+                groups: talent.groups.clone(),
             })
-            .collect();
+        })
+        .collect();
+
+    ListResponse { channels, talents }
+}
+
+/// A talent with the groups they're a member of and their stored channels
+type TalentEntry = (Talent, Vec<String>, Vec<DbChannel>);
+
+/**
+ * Talents with the groups they're a member of and their stored channels
+ */
+fn get_talents() -> Result<Vec<TalentEntry>> {
+    let channels = sqlite::get_db_channels()?;
+    let groups = sqlite::get_db_groups()?;
+
+    let talents = oshi::get_talents()
+        .into_iter()
+        .filter_map(|talent| {
+            let talent_channels: Vec<DbChannel> = talent
+                .channels
+                .iter()
+                .filter_map(|oshi| channels.iter().find(|c| c.id == oshi.id).cloned())
+                .collect();
+
+            if talent_channels.is_empty() {
+                return None;
+            }
+
+            let talent_groups = groups
+                .iter()
+                .filter(|(_, channel_ids)| {
+                    talent_channels.iter().any(|c| channel_ids.contains(&c.id))
+                })
+                .map(|(group, _)| group.clone())
+                .collect();
+
+            Some((talent, talent_groups, talent_channels))
+        })
+        .collect();
+
+    Ok(talents)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::oshi::OshiChannel;
+
+    #[test]
+    fn keeps_the_channel_list() {
+        let talent = Talent {
+            name: "furi".to_string(),
+            aliases: vec!["furina".to_string()],
+            channels: vec![OshiChannel {
+                id: "UCfuri000000000000000000".to_string(),
+                platform: Platforms::Youtube,
+            }],
+        };
+        let channel = DbChannel {
+            id: "UCfuri000000000000000000".to_string(),
+            name: "Furi Ch.".to_string(),
+            handle: Some("@furi".to_string()),
+            ..Default::default()
+        };
+
+        let response = to_response(vec![(talent, vec!["unit".to_string()], vec![channel])]);
+        let json: Value = serde_json::to_value(&response).unwrap();
+
+        let listed = &json["channels"][0];
+        assert_eq!(listed["alias"], "furi");
+        assert_eq!(listed["id"], "UCfuri000000000000000000");
+        assert_eq!(listed["name"], "Furi Ch.");
+        assert_eq!(listed["url"], "https://www.youtube.com/channel/UCfuri000000000000000000");
+        assert_eq!(listed["groups"], json!(["unit"]));
 
-        Ok(serde_json::to_string(&ChannelsResponse { channels: channel_list })?)
+        let talent = &json["talents"][0];
+        assert_eq!(talent["name"], "furi");
+        assert_eq!(talent["aliases"], json!(["furina"]));
+        assert_eq!(talent["channels"][0]["id"], "UCfuri000000000000000000");
     }
 }
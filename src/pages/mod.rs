@@ -1,131 +1,258 @@
 use anyhow::Result;
+use chrono_tz::Tz;
+use sha1::{Digest, Sha1};
 use std::{
-    collections::HashMap,
     sync::{LazyLock, Mutex},
+    time::{Duration, Instant, SystemTime},
 };
 
-use crate::sqlite;
+use crate::{
+    api::{Cursor, VideoKind, VideoStatus},
+    config::CONFIG,
+    error::ApiError,
+    i18n::Locale,
+    metrics,
+    query::DEFAULT_LIMIT,
+    text::TextOptions,
+    time::{Precision, TimeBound},
+};
+pub use cache::Format;
+use cache::{CacheKey, PageCache};
+use table::Table;
 
+mod cache;
+mod groups;
 mod index;
 mod list;
+pub mod table;
 
-#[derive(Clone, Debug)]
+#[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub struct PageContext {
-    pub channel_id: Option<String>,
+    /// Names of the talents to show, every talent when empty
+    pub oshi: Vec<String>,
+    /// Adds the schedules of every member of the group
+    pub group: Option<String>,
+    pub kinds: Vec<VideoKind>,
+    /// Every status when empty
+    pub statuses: Vec<VideoStatus>,
+    pub before: Option<TimeBound>,
+    pub after: Option<TimeBound>,
+    /// Title search
+    pub query: Option<String>,
+    pub cursor: Option<Cursor>,
+    pub limit: usize,
+    /// Zone dates are shown in
+    pub tz: Tz,
+    pub locale: Locale,
+    /// Units of the times left until streams
+    pub precision: Precision,
+    pub text: TextOptions,
+    /// Columns of tabular formats, every column when empty
+    pub fields: Vec<String>,
+    /// Format forced by `?format=` or an extension, kept by links to other pages
+    pub format: Option<Format>,
+}
+
+impl Default for PageContext {
+    fn default() -> Self {
+        PageContext {
+            oshi: vec![],
+            group: None,
+            kinds: VideoKind::VISIBLE.to_vec(),
+            statuses: vec![],
+            before: None,
+            after: None,
+            query: None,
+            cursor: None,
+            limit: DEFAULT_LIMIT,
+            tz: Tz::UTC,
+            locale: Locale::default(),
+            precision: Precision::default(),
+            text: TextOptions::default(),
+            fields: vec![],
+            format: None,
+        }
+    }
+}
+
+/// A rendered page, with a link to the next page of paginated listings
+#[derive(Clone, Debug)]
+pub struct Rendered {
+    pub body: String,
+    pub next: Option<String>,
+    /// Weak validator of the body, set once cached
+    pub etag: Option<String>,
+    /// Latest db write shown by the page, set once cached unless it shows relative times
+    pub modified: Option<SystemTime>,
+}
+
+impl From<String> for Rendered {
+    fn from(body: String) -> Self {
+        Rendered { body, next: None, etag: None, modified: None }
+    }
 }
 
 pub trait Render {
-    async fn render_text(&self, ctx: PageContext) -> Result<String>;
-    async fn render_json(&self, ctx: PageContext) -> Result<String>;
+    async fn render_text(&self, ctx: PageContext) -> Result<Rendered>;
+    async fn render_json(&self, ctx: PageContext) -> Result<Rendered>;
+
+    /**
+     * Rows of the page for CSV, TSV, YAML and Markdown, none when it can't be shown as a table
+     */
+    async fn render_table(&self, _ctx: PageContext) -> Result<Option<Table>> {
+        Ok(None)
+    }
 }
 
 #[derive(Hash, Eq, PartialEq, Copy, Clone, Debug)]
 pub enum Pages {
     Root,
     List,
+    Groups,
 }
 
-static TEXT_CACHE: LazyLock<Mutex<HashMap<Option<String>, String>>> =
-    LazyLock::new(|| Mutex::new(HashMap::new()));
-static JSON_CACHE: LazyLock<Mutex<HashMap<Option<String>, String>>> =
-    LazyLock::new(|| Mutex::new(HashMap::new()));
-
-pub async fn refresh_page(page: Pages) -> Result<()> {
-    {
-        let cache = &TEXT_CACHE;
-        match page {
-            Pages::Root => {
-                let channels = sqlite::get_db_channels()?;
+/// Data written to the db, invalidating the pages that show it
+#[derive(Copy, Clone, Debug)]
+pub enum Change {
+    Videos,
+    Channels,
+}
 
-                let mut channel_ids = channels.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
-                channel_ids.push("all".to_string());
+static CACHE: LazyLock<Mutex<PageCache>> = LazyLock::new(|| {
+    let ttl = Duration::from_secs(CONFIG.cache.ttl_seconds);
+    Mutex::new(PageCache::new(ttl, CONFIG.cache.max_entries))
+});
 
-                for channel_id in channel_ids {
-                    cache.lock().unwrap().remove(&Some(channel_id.clone()));
+pub fn invalidate(change: Change) {
+    let pages: &[Pages] = match change {
+        Change::Videos => &[Pages::Root],
+        // channel names are shown along with videos
+        Change::Channels => &[Pages::Root, Pages::List, Pages::Groups],
+    };
 
-                    let ctx = PageContext { channel_id: Some(channel_id.clone()) };
-                    let content = page.render_text(ctx).await?;
+    tracing::debug!("invalidating {:?} after {:?} changed", pages, change);
 
-                    cache.lock().unwrap().insert(Some(channel_id), content);
-                }
-            }
-            Pages::List => {
-                cache.lock().unwrap().remove(&None);
-
-                let ctx = PageContext { channel_id: None };
-                let content = page.render_text(ctx).await?;
+    let mut cache = CACHE.lock().unwrap();
+    for page in pages {
+        cache.invalidate(*page);
+    }
+}
 
-                cache.lock().unwrap().insert(None, content);
-            }
+impl Pages {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pages::Root => "index",
+            Pages::List => "list",
+            Pages::Groups => "groups",
         }
     }
 
-    {
-        let cache = &JSON_CACHE;
-        match page {
-            Pages::Root => {
-                let channels = sqlite::get_db_channels()?;
-
-                let mut channel_ids = channels.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
-                channel_ids.push("all".to_string());
+    /**
+     * Whether the page shows how long until streams start, which changes as time passes
+     */
+    fn shows_relative_times(&self) -> bool {
+        *self == Pages::Root
+    }
 
-                for channel_id in channel_ids {
-                    cache.lock().unwrap().remove(&Some(channel_id.clone()));
+    pub async fn render(&self, format: Format, ctx: PageContext) -> Result<Rendered> {
+        let key = CacheKey { page: *self, format, ctx };
 
-                    let ctx = PageContext { channel_id: Some(channel_id.clone()) };
-                    let content = page.render_json(ctx).await?;
+        let (generation, modified) = {
+            let cache = CACHE.lock().unwrap();
 
-                    cache.lock().unwrap().insert(Some(channel_id), content);
-                }
+            if let Some(content) = cache.get(&key, Instant::now()) {
+                tracing::debug!("cache hit for {:?}", &key);
+                metrics::record_cache_lookup(self.name(), &format.to_string(), true);
+                return Ok(content);
             }
-            Pages::List => {
-                cache.lock().unwrap().remove(&None);
 
-                let ctx = PageContext { channel_id: None };
-                let content = page.render_json(ctx).await?;
+            (cache.generation(*self), cache.modified(*self))
+        };
+
+        tracing::debug!("cache miss for {:?}", &key);
+        metrics::record_cache_lookup(self.name(), &format.to_string(), false);
 
-                cache.lock().unwrap().insert(None, content);
+        let ctx = key.ctx.clone();
+        let content = match (self, format) {
+            (Pages::Root, Format::Text) => index::Page {}.render_text(ctx).await?,
+            (Pages::Root, Format::Json) => index::Page {}.render_json(ctx).await?,
+            (Pages::List, Format::Text) => list::Page {}.render_text(ctx).await?,
+            (Pages::List, Format::Json) => list::Page {}.render_json(ctx).await?,
+            (Pages::Groups, Format::Text) => groups::Page {}.render_text(ctx).await?,
+            (Pages::Groups, Format::Json) => groups::Page {}.render_json(ctx).await?,
+            (page, format) => {
+                let table = match page {
+                    Pages::Root => index::Page {}.render_table(ctx).await?,
+                    Pages::List => list::Page {}.render_table(ctx).await?,
+                    Pages::Groups => groups::Page {}.render_table(ctx).await?,
+                };
+                let Some(table) = table else {
+                    return Err(ApiError::NotAcceptable(format!(
+                        "{} is not available as {format}, expected json or text",
+                        page.name()
+                    ))
+                    .into());
+                };
+
+                table.select(&key.ctx.fields)?.render(format)?
             }
-        }
-    }
+        };
 
-    Ok(())
-}
+        // weak, as every content coding of the body shares it
+        let etag = format!("W/\"{}\"", hex::encode(Sha1::digest(&content.body)));
+        // times relative to now change the page without any write, only its etag follows them
+        let modified = (!self.shows_relative_times()).then_some(modified);
+        let content = Rendered { etag: Some(etag), modified, ..content };
 
-impl Render for Pages {
-    async fn render_text(&self, ctx: PageContext) -> Result<String> {
-        if let Some(content) = TEXT_CACHE.lock().unwrap().get(&ctx.channel_id) {
-            tracing::debug!("cache hit for {:?} text", &ctx.channel_id);
-            return Ok(content.clone());
-        }
+        CACHE.lock().unwrap().insert(key, content.clone(), generation, Instant::now());
 
-        tracing::debug!("cache miss for {:?} text", &ctx.channel_id);
+        Ok(content)
+    }
+}
 
-        let content = match self {
-            Pages::Root => index::Page {}.render_text(ctx.clone()).await?,
-            Pages::List => list::Page {}.render_text(ctx.clone()).await?,
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite;
 
-        TEXT_CACHE.lock().unwrap().insert(ctx.channel_id, content.clone());
+    const PAGES: [Pages; 3] = [Pages::Root, Pages::List, Pages::Groups];
+    const FORMATS: [Format; 6] =
+        [Format::Text, Format::Json, Format::Csv, Format::Tsv, Format::Yaml, Format::Markdown];
 
-        Ok(content)
+    fn is_cached(page: Pages, format: Format) -> bool {
+        let key = CacheKey { page, format, ctx: PageContext::default() };
+        CACHE.lock().unwrap().get(&key, Instant::now()).is_some()
     }
 
-    async fn render_json(&self, ctx: PageContext) -> Result<String> {
-        if let Some(content) = JSON_CACHE.lock().unwrap().get(&ctx.channel_id) {
-            tracing::debug!("cache hit for {:?} json", &ctx.channel_id);
-            return Ok(content.clone());
-        }
+    #[tokio::test]
+    async fn renders_and_invalidates_pages() {
+        sqlite::init_db();
 
-        tracing::debug!("cache miss for {:?} json", &ctx.channel_id);
+        for page in PAGES {
+            for format in FORMATS {
+                let rendered = page.render(format, PageContext::default()).await.unwrap();
+                assert!(rendered.etag.as_ref().is_some_and(|etag| etag.starts_with("W/\"")));
+                assert_eq!(rendered.modified.is_some(), page != Pages::Root, "{page:?} {format:?}");
+                assert!(is_cached(page, format), "{page:?} {format:?}");
 
-        let content = match self {
-            Pages::Root => index::Page {}.render_json(ctx.clone()).await?,
-            Pages::List => list::Page {}.render_json(ctx.clone()).await?,
-        };
+                let cached = page.render(format, PageContext::default()).await.unwrap();
+                assert_eq!(cached.body, rendered.body, "{page:?} {format:?}");
+                assert_eq!(cached.etag, rendered.etag, "{page:?} {format:?}");
+            }
+        }
 
-        JSON_CACHE.lock().unwrap().insert(ctx.channel_id, content.clone());
+        invalidate(Change::Videos);
+        for format in [Format::Text, Format::Json] {
+            assert!(!is_cached(Pages::Root, format));
+            assert!(is_cached(Pages::List, format));
+            assert!(is_cached(Pages::Groups, format));
+        }
 
-        Ok(content)
+        invalidate(Change::Channels);
+        for page in PAGES {
+            for format in FORMATS {
+                assert!(!is_cached(page, format), "{page:?} {format:?}");
+            }
+        }
     }
 }
@@ -0,0 +1,308 @@
+use anyhow::{Result, bail};
+use axum::{extract::FromRequestParts, http::request::Parts};
+use serde::{Deserialize, Serialize, Serializer, ser::SerializeMap};
+use std::fmt;
+
+use crate::{error::ApiError, query};
+
+use super::{Format, Rendered};
+
+/// A value of a table, kept typed for YAML
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Cell {
+    Text(String),
+    Number(i64),
+    Bool(bool),
+    Empty,
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cell::Text(text) => f.write_str(text),
+            Cell::Number(n) => write!(f, "{n}"),
+            Cell::Bool(b) => write!(f, "{b}"),
+            Cell::Empty => Ok(()),
+        }
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::Text(text)
+    }
+}
+
+impl From<i64> for Cell {
+    fn from(n: i64) -> Self {
+        Cell::Number(n)
+    }
+}
+
+impl From<bool> for Cell {
+    fn from(b: bool) -> Self {
+        Cell::Bool(b)
+    }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Cell::Empty, Into::into)
+    }
+}
+
+/// Rows of a page for CSV, TSV, YAML and Markdown, with a link to the next page of paginated listings
+#[derive(Debug)]
+pub struct Table {
+    pub columns: Vec<&'static str>,
+    pub rows: Vec<Vec<Cell>>,
+    pub next: Option<String>,
+}
+
+impl Table {
+    /**
+     * Keeps the columns named in `fields` in that order, every column when none are
+     */
+    pub fn select(self, fields: &[String]) -> Result<Table, ApiError> {
+        if fields.is_empty() {
+            return Ok(self);
+        }
+
+        let indices = fields
+            .iter()
+            .map(|field| {
+                self.columns.iter().position(|column| column == field).ok_or_else(|| {
+                    ApiError::BadRequest(format!(
+                        "unknown field: {field}, expected {}",
+                        self.columns.join(", ")
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Table {
+            columns: indices.iter().map(|i| self.columns[*i]).collect(),
+            rows: self
+                .rows
+                .into_iter()
+                .map(|row| indices.iter().map(|i| row[*i].clone()).collect())
+                .collect(),
+            next: self.next,
+        })
+    }
+
+    pub fn render(&self, format: Format) -> Result<Rendered> {
+        let body = match format {
+            Format::Csv => self.to_delimited(b',')?,
+            Format::Tsv => self.to_delimited(b'\t')?,
+            Format::Yaml => self.to_yaml()?,
+            Format::Markdown => self.to_markdown(),
+            Format::Text | Format::Json => bail!("{format} is not a table format"),
+        };
+
+        Ok(Rendered { next: self.next.clone(), ..body.into() })
+    }
+
+    /**
+     * A header line and a line per row, fields being quoted when they contain the delimiter
+     */
+    fn to_delimited(&self, delimiter: u8) -> Result<String> {
+        let mut writer = csv::WriterBuilder::new().delimiter(delimiter).from_writer(vec![]);
+
+        writer.write_record(&self.columns)?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(escape_formula))?;
+        }
+
+        let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+        Ok(String::from_utf8(bytes)?)
+    }
+
+    /**
+     * A list of rows, each a map of its columns in order
+     */
+    fn to_yaml(&self) -> Result<String> {
+        let records: Vec<Record> =
+            self.rows.iter().map(|cells| Record { columns: &self.columns, cells }).collect();
+
+        Ok(serde_yaml::to_string(&records)?)
+    }
+
+    fn to_markdown(&self) -> String {
+        let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+
+        let mut markdown = line(self.columns.iter().map(|column| column.to_string()).collect());
+        markdown.push_str(&line(self.columns.iter().map(|_| "---".to_string()).collect()));
+
+        for row in &self.rows {
+            markdown.push_str(&line(row.iter().map(escape_markdown).collect()));
+        }
+
+        markdown
+    }
+}
+
+/**
+ * Keeps spreadsheets from running text as a formula, numbers being left alone
+ */
+fn escape_formula(cell: &Cell) -> String {
+    match cell {
+        Cell::Text(text) if text.starts_with(['=', '+', '-', '@']) => format!("'{text}"),
+        cell => cell.to_string(),
+    }
+}
+
+/**
+ * Keeps a value inside its cell, pipes ending it and line breaks the whole row otherwise
+ */
+fn escape_markdown(cell: &Cell) -> String {
+    cell.to_string().replace('|', "\\|").replace("\r\n", "<br>").replace('\n', "<br>")
+}
+
+struct Record<'a> {
+    columns: &'a [&'static str],
+    cells: &'a [Cell],
+}
+
+impl Serialize for Record<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+
+        for (column, cell) in self.columns.iter().zip(self.cells) {
+            map.serialize_entry(column, cell)?;
+        }
+
+        map.end()
+    }
+}
+
+/// Columns asked for with `?fields=`, every column when empty
+#[derive(Clone, Debug, Default)]
+pub struct Fields(pub Vec<String>);
+
+#[derive(Deserialize)]
+struct FieldsQuery {
+    fields: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Fields {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query =
+            serde_urlencoded::from_str::<FieldsQuery>(parts.uri.query().unwrap_or_default())
+                .map_err(ApiError::bad_request)?;
+
+        Ok(Fields(query.fields.as_deref().map(query::parse_fields).unwrap_or_default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        Table {
+            columns: vec!["status", "title", "channel", "collab", "concurrent_viewers"],
+            rows: vec![
+                vec![
+                    "live".to_string().into(),
+                    "【歌枠】karaoke, \"chill\" | requests".to_string().into(),
+                    "Furi Ch.".to_string().into(),
+                    false.into(),
+                    1234.into(),
+                ],
+                vec![
+                    "upcoming".to_string().into(),
+                    "collab\tstream\nwith everyone".to_string().into(),
+                    "Raki Ch.".to_string().into(),
+                    true.into(),
+                    None::<i64>.into(),
+                ],
+            ],
+            next: Some("/?cursor=00".to_string()),
+        }
+    }
+
+    #[test]
+    fn matches_golden_files() {
+        let golden = [
+            (Format::Csv, include_str!("../../tests/golden/videos.csv")),
+            (Format::Tsv, include_str!("../../tests/golden/videos.tsv")),
+            (Format::Yaml, include_str!("../../tests/golden/videos.yaml")),
+            (Format::Markdown, include_str!("../../tests/golden/videos.md")),
+        ];
+
+        for (format, expected) in golden {
+            let rendered = table().render(format).unwrap();
+
+            assert_eq!(rendered.body, expected, "{format}");
+            assert_eq!(rendered.next.as_deref(), Some("/?cursor=00"));
+        }
+    }
+
+    #[test]
+    fn matches_golden_files_with_fields() {
+        let fields = ["title", "status"].map(String::from);
+        let golden = [
+            (Format::Csv, include_str!("../../tests/golden/videos_fields.csv")),
+            (Format::Tsv, include_str!("../../tests/golden/videos_fields.tsv")),
+            (Format::Yaml, include_str!("../../tests/golden/videos_fields.yaml")),
+            (Format::Markdown, include_str!("../../tests/golden/videos_fields.md")),
+        ];
+
+        for (format, expected) in golden {
+            let rendered = table().select(&fields).unwrap().render(format).unwrap();
+            assert_eq!(rendered.body, expected, "{format}");
+        }
+
+        let unknown = table().select(&["title".to_string(), "thumbnail".to_string()]).unwrap_err();
+        assert_eq!(
+            unknown,
+            ApiError::BadRequest(
+                "unknown field: thumbnail, expected status, title, channel, collab, concurrent_viewers"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn escapes_formulas() {
+        let table = Table {
+            columns: vec!["title", "channel", "seconds_until_start"],
+            rows: vec![vec![
+                "=HYPERLINK(\"https://example.com\")".to_string().into(),
+                "@furi".to_string().into(),
+                (-90).into(),
+            ]],
+            next: None,
+        };
+
+        assert_eq!(
+            table.render(Format::Csv).unwrap().body,
+            "title,channel,seconds_until_start\n\"'=HYPERLINK(\"\"https://example.com\"\")\",'@furi,-90\n"
+        );
+
+        for text in ["+1", "-1 day", "@here", "=1+1"] {
+            assert_eq!(escape_formula(&text.to_string().into()), format!("'{text}"));
+        }
+        assert_eq!(escape_formula(&"1+1".to_string().into()), "1+1");
+        assert_eq!(escape_formula(&Cell::Number(-1)), "-1");
+    }
+
+    #[test]
+    fn selects_fields() {
+        let fields = ["title", "status"].map(String::from);
+        let selected = table().select(&fields).unwrap();
+
+        assert_eq!(selected.columns, ["title", "status"]);
+        assert_eq!(
+            selected.rows[1],
+            ["collab\tstream\nwith everyone".to_string().into(), "upcoming".to_string().into()]
+        );
+
+        assert!(matches!(table().select(&["thumbnail".to_string()]), Err(ApiError::BadRequest(_))));
+        assert!(table().render(Format::Json).is_err());
+    }
+}
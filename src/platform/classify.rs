@@ -0,0 +1,128 @@
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{
+    api::{DbVideo, VideoKind},
+    config::{CONFIG, ClassifyConfig},
+};
+
+/**
+ * Tags a video using the API fields and the configured title, duration and schedule heuristics
+ */
+pub fn classify_video(video: &DbVideo) -> VideoKind {
+    classify(video, &CONFIG.classify, Utc::now())
+}
+
+fn classify(video: &DbVideo, config: &ClassifyConfig, now: DateTime<Utc>) -> VideoKind {
+    let title = video.title.to_lowercase();
+    let has_keyword = |keywords: &[String]| {
+        keywords.iter().any(|keyword| title.contains(&keyword.to_lowercase()))
+    };
+
+    if has_keyword(&config.placeholder_keywords) {
+        return VideoKind::Placeholder;
+    }
+
+    if has_keyword(&config.members_keywords) {
+        return VideoKind::MembersOnly;
+    }
+
+    // a frame scheduled far ahead is only a placeholder when it's too short to be a video
+    if is_frame(video, config) && is_far_ahead(video, config, now) {
+        return VideoKind::Placeholder;
+    }
+
+    if video.premiere {
+        return VideoKind::Premiere;
+    }
+
+    VideoKind::Live
+}
+
+/**
+ * Whether the video is a premiere of a blank frame, live streams reporting no length until they end
+ */
+fn is_frame(video: &DbVideo, config: &ClassifyConfig) -> bool {
+    video.premiere
+        && video.duration.is_some_and(|duration| duration <= config.placeholder_max_duration)
+}
+
+fn is_far_ahead(video: &DbVideo, config: &ClassifyConfig, now: DateTime<Utc>) -> bool {
+    if video.start_time.is_some() {
+        return false;
+    }
+
+    match DateTime::parse_from_rfc3339(&video.scheduled_time) {
+        Ok(scheduled) => {
+            scheduled.with_timezone(&Utc) - now > TimeDelta::days(config.placeholder_days)
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_videos() {
+        let config = ClassifyConfig {
+            placeholder_keywords: vec!["Free Chat".to_string(), "フリーチャット".to_string()],
+            members_keywords: vec!["members only".to_string(), "メン限".to_string()],
+            placeholder_days: 30,
+            placeholder_max_duration: 60,
+        };
+        let now = "2026-01-01T12:00:00Z".parse().unwrap();
+
+        let cases = [
+            // title, premiere, duration, scheduled time, start time, kind
+            ("【歌枠】karaoke", false, Some(0), "2026-01-01T20:00:00Z", None, VideoKind::Live),
+            ("MV premiere", true, Some(240), "2026-01-01T20:00:00Z", None, VideoKind::Premiere),
+            ("【FREE CHAT】", false, Some(0), "2026-01-02T00:00:00Z", None, VideoKind::Placeholder),
+            ("フリーチャット", false, None, "2026-01-02T00:00:00Z", None, VideoKind::Placeholder),
+            (
+                "【メン限】zatsudan",
+                false,
+                Some(0),
+                "2026-01-01T20:00:00Z",
+                None,
+                VideoKind::MembersOnly,
+            ),
+            (
+                "Members Only MV",
+                true,
+                Some(240),
+                "2026-01-01T20:00:00Z",
+                None,
+                VideoKind::MembersOnly,
+            ),
+            // far ahead, but only short frames are placeholders
+            ("3D live", false, Some(0), "2026-03-01T00:00:00Z", None, VideoKind::Live),
+            ("【メン限】3D", false, Some(0), "2026-03-01T00:00:00Z", None, VideoKind::MembersOnly),
+            ("MV premiere", true, Some(240), "2026-03-01T00:00:00Z", None, VideoKind::Premiere),
+            ("schedule", true, Some(10), "2026-03-01T00:00:00Z", None, VideoKind::Placeholder),
+            ("teaser", true, Some(10), "2026-01-01T20:00:00Z", None, VideoKind::Premiere),
+            (
+                "started early",
+                true,
+                Some(10),
+                "2026-03-01T00:00:00Z",
+                Some("2026-01-01T11:00:00Z"),
+                VideoKind::Premiere,
+            ),
+            ("not a date", true, Some(10), "soon", None, VideoKind::Premiere),
+        ];
+
+        for (title, premiere, duration, scheduled_time, start_time, kind) in cases {
+            let video = DbVideo {
+                title: title.to_string(),
+                premiere,
+                duration,
+                scheduled_time: scheduled_time.to_string(),
+                start_time: start_time.map(String::from),
+                ..Default::default()
+            };
+
+            assert_eq!(classify(&video, &config, now), kind, "{title}");
+        }
+    }
+}
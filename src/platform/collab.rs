@@ -0,0 +1,130 @@
+use crate::api::DbChannel;
+
+use super::{PlatformVideo, Platforms};
+
+/**
+ * Finds tracked channels mentioned in a video hosted by another channel
+ */
+pub fn get_participants(video: &PlatformVideo, channels: &[DbChannel]) -> Vec<String> {
+    let haystack = format!("{}\n{}", video.video.title, video.description).to_lowercase();
+
+    channels
+        .iter()
+        .filter(|channel| channel.id != video.video.channel_id)
+        .filter(|channel| {
+            get_needles(channel).iter().any(|needle| contains_word(&haystack, needle))
+        })
+        .map(|channel| channel.id.clone())
+        .collect()
+}
+
+fn get_needles(channel: &DbChannel) -> Vec<String> {
+    let mut needles = vec![];
+
+    // twitch ids are plain numbers that would match all sorts of titles
+    if channel.platform == Platforms::Youtube {
+        needles.push(channel.id.to_lowercase());
+    }
+
+    let name = channel.name.trim().to_lowercase();
+    if !name.is_empty() {
+        needles.push(name);
+    }
+
+    // youtube handles come with their `@`, twitch logins without
+    if let Some(handle) = &channel.handle {
+        needles.push(format!("@{}", handle.trim_start_matches('@').to_lowercase()));
+    }
+
+    needles
+}
+
+/**
+ * Whether `needle` appears in `haystack` as whole words, not as part of a longer name or handle
+ */
+fn contains_word(haystack: &str, needle: &str) -> bool {
+    haystack.match_indices(needle).any(|(start, _)| {
+        let before = haystack[..start].chars().next_back();
+        let mut after = haystack[start + needle.len()..].chars();
+
+        !before.is_some_and(is_word) && !continues_word(after.next(), after.next())
+    })
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/**
+ * Whether the characters after a match carry on its word, `.` and `-` only do inside handles like `@ame.watson`
+ */
+fn continues_word(next: Option<char>, then: Option<char>) -> bool {
+    match next {
+        Some('.' | '-') => then.is_some_and(is_word),
+        Some(c) => is_word(c),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DbVideo;
+
+    fn channel(id: &str, name: &str, handle: &str) -> DbChannel {
+        DbChannel {
+            id: id.to_string(),
+            name: name.to_string(),
+            handle: Some(handle.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn video(title: &str, description: &str) -> PlatformVideo {
+        PlatformVideo {
+            video: DbVideo {
+                channel_id: "UCkD8mS5QsVdHPx0NskcObZg".to_string(),
+                title: title.to_string(),
+                ..Default::default()
+            },
+            description: description.to_string(),
+        }
+    }
+
+    #[test]
+    fn finds_participants() {
+        let channels = [
+            channel("UCMwGHR0BTZuLsmjY_NT5Pwg", "Ina", "@ninomaeinanis"),
+            channel("UCyl1z3jo3XHR1riLFKG5UAg", "Amelia Watson", "@watsonameliaen"),
+            channel("UCkD8mS5QsVdHPx0NskcObZg", "Host", "@host"),
+        ];
+
+        let cases = [
+            ("【COLLAB】 with Ina!", "", vec!["UCMwGHR0BTZuLsmjY_NT5Pwg"]),
+            ("karaoke", "ft. @NinomaeInanis.", vec!["UCMwGHR0BTZuLsmjY_NT5Pwg"]),
+            ("karaoke", "@watsonameliaen & @host", vec!["UCyl1z3jo3XHR1riLFKG5UAg"]),
+            (
+                "karaoke",
+                "youtube.com/channel/UCyl1z3jo3XHR1riLFKG5UAg",
+                vec!["UCyl1z3jo3XHR1riLFKG5UAg"],
+            ),
+            (
+                "AMELIA WATSON x Ina",
+                "",
+                vec!["UCMwGHR0BTZuLsmjY_NT5Pwg", "UCyl1z3jo3XHR1riLFKG5UAg"],
+            ),
+            // the host is never its own participant
+            ("Host plays games", "", vec![]),
+            // substrings of longer words, handles and ids
+            ("Inaugural stream", "", vec![]),
+            ("karaoke", "@ninomaeinanis_fan @watsonameliaen.alt", vec![]),
+            ("karaoke", "UCyl1z3jo3XHR1riLFKG5UAgX", vec![]),
+        ];
+
+        for (title, description, expected) in cases {
+            let mut participants = get_participants(&video(title, description), &channels);
+            participants.sort();
+            assert_eq!(participants, expected, "{title} {description}");
+        }
+    }
+}
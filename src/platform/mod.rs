@@ -0,0 +1,121 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use utoipa::ToSchema;
+
+use crate::{
+    api::{DbChannel, DbVideo},
+    twitch, youtube,
+};
+
+mod classify;
+pub mod collab;
+
+/// A stream as returned by a platform, before it is stored
+#[derive(Debug, Clone)]
+pub struct PlatformVideo {
+    pub video: DbVideo,
+    /// Full description, only the excerpt in `video` is stored
+    pub description: String,
+}
+
+pub trait Platform {
+    /**
+     * Fetches a channel's details
+     */
+    async fn get_channel(&self, channel_id: &str) -> Result<DbChannel>;
+    /**
+     * Lists the ids of a channel's recent, upcoming and live streams
+     */
+    async fn get_stream_ids(&self, channel_id: &str) -> Result<Vec<String>>;
+    /**
+     * Fetches the details of streams, leaving out the ones that no longer exist
+     */
+    async fn get_streams(&self, stream_ids: &[String]) -> Result<Vec<PlatformVideo>>;
+}
+
+#[derive(Debug, Clone, Copy, Default, Hash, Eq, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Platforms {
+    #[default]
+    Youtube,
+    Twitch,
+}
+
+impl Platforms {
+    pub const ALL: [Platforms; 2] = [Platforms::Youtube, Platforms::Twitch];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Platforms::Youtube => "youtube",
+            Platforms::Twitch => "twitch",
+        }
+    }
+
+    pub fn video_url(&self, video: &DbVideo) -> String {
+        match self {
+            Platforms::Youtube => format!("https://www.youtube.com/watch?v={}", video.id),
+            Platforms::Twitch => format!(
+                "https://www.twitch.tv/{}",
+                video.channel_handle.as_deref().unwrap_or(&video.channel_id)
+            ),
+        }
+    }
+
+    pub fn channel_url(&self, channel: &DbChannel) -> String {
+        match self {
+            Platforms::Youtube => format!("https://www.youtube.com/channel/{}", channel.id),
+            Platforms::Twitch => format!(
+                "https://www.twitch.tv/{}",
+                channel.handle.as_deref().unwrap_or(&channel.id)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Platforms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Platforms {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Platforms::ALL
+            .into_iter()
+            .find(|platform| platform.as_str() == s)
+            .ok_or_else(|| anyhow!("unknown platform: {s}"))
+    }
+}
+
+impl Platform for Platforms {
+    async fn get_channel(&self, channel_id: &str) -> Result<DbChannel> {
+        match self {
+            Platforms::Youtube => youtube::Youtube {}.get_channel(channel_id).await,
+            Platforms::Twitch => twitch::Twitch {}.get_channel(channel_id).await,
+        }
+    }
+
+    async fn get_stream_ids(&self, channel_id: &str) -> Result<Vec<String>> {
+        match self {
+            Platforms::Youtube => youtube::Youtube {}.get_stream_ids(channel_id).await,
+            Platforms::Twitch => twitch::Twitch {}.get_stream_ids(channel_id).await,
+        }
+    }
+
+    async fn get_streams(&self, stream_ids: &[String]) -> Result<Vec<PlatformVideo>> {
+        let mut streams = match self {
+            Platforms::Youtube => youtube::Youtube {}.get_streams(stream_ids).await?,
+            Platforms::Twitch => twitch::Twitch {}.get_streams(stream_ids).await?,
+        };
+
+        // streams of every platform are tagged the same way
+        for stream in &mut streams {
+            stream.video.kind = classify::classify_video(&stream.video);
+        }
+
+        Ok(streams)
+    }
+}
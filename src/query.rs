@@ -0,0 +1,476 @@
+use anyhow::{Result, anyhow};
+use axum::http::HeaderMap;
+use chrono::Utc;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::IntoParams;
+
+use crate::{
+    api::{Cursor, DbVideo, VideoKind},
+    colors::ColorMode,
+    error::ApiError,
+    events::{self, Event},
+    i18n::{self, Locale},
+    oshi,
+    pages::PageContext,
+    sqlite::{self, VideoFilter},
+    text::{self, Layout, TextOptions},
+    time::{self, Precision, TimeBound},
+};
+
+/// Videos a page lists when no `?limit=` is given
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// Most videos a single page lists
+pub const MAX_LIMIT: usize = 100;
+
+/// Header holding a time zone, like the `TZ` env var
+pub const TZ_HEADER: &str = "tz";
+
+/// Query parameters of `GET /`, validated into a [`PageContext`]
+#[derive(Debug, Default, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RootQuery {
+    /// Comma-separated talent names or aliases
+    pub oshi: Option<String>,
+    /// Adds the streams of every member of the group
+    pub group: Option<String>,
+    /// Comma-separated `live`, `premiere`, `members-only` or `placeholder`, all but placeholders by default
+    pub kind: Option<String>,
+    /// Comma-separated `live` or `upcoming`
+    pub status: Option<String>,
+    /// RFC3339 date, or an offset from now like `24h` or `-30m`
+    pub before: Option<String>,
+    /// RFC3339 date, or an offset from now like `24h` or `-30m`
+    pub after: Option<String>,
+    /// 1 to 100, 10 by default
+    pub limit: Option<String>,
+    /// Text the title contains, ignoring case
+    pub q: Option<String>,
+    /// Continues a listing, taken from a `next` link
+    pub cursor: Option<String>,
+    /// IANA time zone dates are shown in, like `Asia/Tokyo`, UTC by default
+    pub tz: Option<String>,
+    /// `en` or `ja`, taken from `Accept-Language` by default
+    pub lang: Option<String>,
+    /// Units of the times left until streams, 1 to 4 optionally followed by `floor`, `round` or `ceil`, like `2,round`
+    pub precision: Option<String>,
+    /// Text pages only, `never`, `always`, `16`, `256` or `truecolor`, 256 colours by default
+    pub color: Option<String>,
+    /// Text pages only, columns titles are wrapped or truncated to fit in, 20 to 1000
+    pub width: Option<String>,
+    /// Text pages only, `full` or `compact` for a line per stream
+    pub layout: Option<String>,
+    /// CSV, TSV, YAML and Markdown only, comma-separated columns to show in that order
+    pub fields: Option<String>,
+}
+
+impl RootQuery {
+    /**
+     * Falls back to the time zone a CLI sends in the `TZ` header when `?tz=` isn't given,
+     * to the language of `Accept-Language` when `?lang=` isn't,
+     * and turns colours off when a `No-Color` header is sent without `?color=`
+     */
+    pub fn with_headers(mut self, headers: &HeaderMap) -> Self {
+        if self.tz.is_none() {
+            self.tz = headers.get(TZ_HEADER).and_then(|val| val.to_str().ok()).map(str::to_string);
+        }
+
+        if self.lang.is_none() {
+            self.lang = i18n::from_headers(headers).map(|locale| locale.to_string());
+        }
+
+        if self.color.is_none() && text::no_color(headers) {
+            self.color = Some(ColorMode::Never.to_string());
+        }
+
+        self
+    }
+}
+
+impl TryFrom<RootQuery> for PageContext {
+    type Error = ApiError;
+
+    fn try_from(query: RootQuery) -> Result<Self, ApiError> {
+        let mut ctx = PageContext::default();
+
+        // first, so the errors below are in the language asked for
+        if let Some(lang) = &query.lang {
+            ctx.locale = lang.trim().parse().map_err(ApiError::bad_request)?;
+        }
+
+        if let Some(aliases) = &query.oshi {
+            for alias in split_list(aliases) {
+                let Some(talent) = oshi::get_talent(alias) else {
+                    let suggestion = oshi::suggest_alias(alias);
+                    return Err(ApiError::NotFound(
+                        ctx.locale.not_tracked(alias, suggestion.as_deref()),
+                    ));
+                };
+
+                ctx.oshi.push(talent.name);
+            }
+
+            // keeps `?oshi=a,b` and `?oshi=b,a` on the same cached page
+            ctx.oshi.sort();
+            ctx.oshi.dedup();
+        }
+
+        if let Some(group) = query.group {
+            if !sqlite::get_db_groups()?.contains_key(&group) {
+                return Err(ApiError::NotFound(ctx.locale.group_not_tracked(&group)));
+            }
+
+            ctx.group = Some(group);
+        }
+
+        if let Some(kinds) = &query.kind {
+            ctx.kinds = parse_list(kinds).map_err(ApiError::bad_request)?;
+        }
+
+        if let Some(statuses) = &query.status {
+            ctx.statuses = parse_list(statuses).map_err(ApiError::bad_request)?;
+        }
+
+        ctx.before =
+            query.before.as_deref().map(str::parse).transpose().map_err(ApiError::bad_request)?;
+        ctx.after = query
+            .after
+            .as_deref()
+            .map(str::parse::<TimeBound>)
+            .transpose()
+            .map_err(ApiError::bad_request)?;
+
+        if let Some(limit) = &query.limit {
+            ctx.limit = match limit.parse() {
+                Ok(limit) if (1..=MAX_LIMIT).contains(&limit) => limit,
+                _ => {
+                    return Err(ApiError::BadRequest(format!(
+                        "invalid limit: {limit}, expected 1 to {MAX_LIMIT}"
+                    )));
+                }
+            };
+        }
+
+        ctx.query = query.q.map(|q| q.trim().to_string()).filter(|q| !q.is_empty());
+        ctx.cursor =
+            query.cursor.as_deref().map(str::parse).transpose().map_err(ApiError::bad_request)?;
+
+        if let Some(tz) = &query.tz {
+            ctx.tz = time::parse_tz(tz.trim()).map_err(ApiError::bad_request)?;
+        }
+
+        if let Some(precision) = &query.precision {
+            ctx.precision = precision.parse().map_err(ApiError::bad_request)?;
+        }
+
+        ctx.fields = query.fields.as_deref().map(parse_fields).unwrap_or_default();
+
+        ctx.text = TextOptions::parse(
+            query.color.as_deref(),
+            query.width.as_deref(),
+            query.layout.as_deref(),
+        )?;
+
+        Ok(ctx)
+    }
+}
+
+impl From<&PageContext> for RootQuery {
+    /**
+     * The parameters that lead back to the page, leaving out defaults
+     */
+    fn from(ctx: &PageContext) -> Self {
+        let join = |list: Vec<String>| (!list.is_empty()).then(|| list.join(","));
+
+        RootQuery {
+            oshi: join(ctx.oshi.clone()),
+            group: ctx.group.clone(),
+            kind: (ctx.kinds != VideoKind::VISIBLE).then(|| {
+                ctx.kinds.iter().map(|kind| kind.to_string()).collect::<Vec<_>>().join(",")
+            }),
+            status: join(ctx.statuses.iter().map(|status| status.to_string()).collect()),
+            before: ctx.before.map(|before| before.to_string()),
+            after: ctx.after.map(|after| after.to_string()),
+            limit: (ctx.limit != DEFAULT_LIMIT).then(|| ctx.limit.to_string()),
+            q: ctx.query.clone(),
+            cursor: ctx.cursor.as_ref().map(|cursor| cursor.to_string()),
+            tz: (ctx.tz != Tz::UTC).then(|| ctx.tz.name().to_string()),
+            lang: (ctx.locale != Locale::default()).then(|| ctx.locale.to_string()),
+            precision: (ctx.precision != Precision::default()).then(|| ctx.precision.to_string()),
+            color: (ctx.text.color != ColorMode::default()).then(|| ctx.text.color.to_string()),
+            width: ctx.text.width.map(|width| width.to_string()),
+            layout: (ctx.text.layout != Layout::default()).then(|| ctx.text.layout.to_string()),
+            fields: join(ctx.fields.clone()),
+        }
+    }
+}
+
+/**
+ * Link to the page served at `path`
+ */
+pub fn page_link(path: &str, ctx: &PageContext) -> String {
+    let mut query = serde_urlencoded::to_string(RootQuery::from(ctx)).unwrap_or_default();
+
+    // the format is negotiated apart from the other parameters
+    if let Some(format) = ctx.format {
+        let separator = if query.is_empty() { "" } else { "&" };
+        query.push_str(&format!("{separator}format={format}"));
+    }
+
+    match query.is_empty() {
+        true => path.to_string(),
+        false => format!("{path}?{query}"),
+    }
+}
+
+/**
+ * Column names of `?fields=`, in order and without repeats
+ */
+pub fn parse_fields(s: &str) -> Vec<String> {
+    let mut fields: Vec<String> = vec![];
+
+    for field in split_list(s) {
+        if !fields.iter().any(|f| f == field) {
+            fields.push(field.to_string());
+        }
+    }
+
+    fields
+}
+
+fn split_list(s: &str) -> impl Iterator<Item = &str> {
+    s.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+fn parse_list<T: FromStr<Err = anyhow::Error>>(s: &str) -> Result<Vec<T>> {
+    let list = split_list(s).map(str::parse).collect::<Result<Vec<T>>>()?;
+
+    match list.is_empty() {
+        true => Err(anyhow!("empty list: {s}")),
+        false => Ok(list),
+    }
+}
+
+/// A page of events, with the context of the following page when some are left
+pub struct VideoPage {
+    pub events: Vec<Event>,
+    /// Events left after this page
+    pub remaining: usize,
+    pub next: Option<PageContext>,
+}
+
+/**
+ * Fetches the events a page lists, failing when a talent or group isn't tracked
+ */
+pub fn get_video_page(ctx: &PageContext) -> Result<VideoPage, ApiError> {
+    // simulcasts are merged before paging, so an event is never split across pages
+    let videos = get_videos(ctx, get_channel_ids(ctx)?)?;
+    let (events, remaining) =
+        page_events(events::group_videos(videos), ctx.cursor.as_ref(), ctx.limit);
+
+    let next = events
+        .last()
+        .filter(|_| remaining > 0)
+        .map(|last| PageContext { cursor: Some(last.cursor()), ..ctx.clone() });
+
+    Ok(VideoPage { events, remaining, next })
+}
+
+/**
+ * The events after `cursor`, up to `limit`, along with how many are left after them
+ */
+fn page_events(events: Vec<Event>, cursor: Option<&Cursor>, limit: usize) -> (Vec<Event>, usize) {
+    let mut events: Vec<Event> = events
+        .into_iter()
+        .filter(|event| {
+            cursor.is_none_or(|cursor| {
+                let first = event.cursor();
+                (&first.scheduled_time, &first.id) > (&cursor.scheduled_time, &cursor.id)
+            })
+        })
+        .collect();
+
+    let remaining = events.len().saturating_sub(limit);
+    events.truncate(limit);
+
+    (events, remaining)
+}
+
+/**
+ * Channels to show the schedules of, an empty list meaning every tracked channel
+ */
+fn get_channel_ids(ctx: &PageContext) -> Result<Vec<String>, ApiError> {
+    let mut channel_ids = vec![];
+
+    for name in &ctx.oshi {
+        match oshi::get_talent(name) {
+            Some(talent) => channel_ids.extend(talent.channel_ids()),
+            None => return Err(ApiError::NotFound(ctx.locale.not_tracked(name, None))),
+        }
+    }
+
+    if let Some(group) = &ctx.group {
+        match sqlite::get_db_groups()?.get(group) {
+            Some(group_channel_ids) if !group_channel_ids.is_empty() => {
+                channel_ids.extend(group_channel_ids.iter().cloned())
+            }
+            _ => return Err(ApiError::NotFound(ctx.locale.group_not_tracked(group))),
+        }
+    }
+
+    Ok(channel_ids)
+}
+
+/**
+ * Every video matching the page's filters, the cursor and limit being applied to events
+ */
+fn get_videos(ctx: &PageContext, channel_ids: Vec<String>) -> Result<Vec<DbVideo>> {
+    let now = Utc::now();
+    let filter = VideoFilter {
+        channel_ids,
+        kinds: ctx.kinds.clone(),
+        statuses: ctx.statuses.clone(),
+        before: ctx.before.map(|before| before.resolve(now)),
+        after: ctx.after.map(|after| after.resolve(now)),
+        title: ctx.query.clone(),
+        ..Default::default()
+    };
+
+    sqlite::get_db_upcoming_videos(&filter)
+        .map(|(videos, _)| videos)
+        .map_err(|e| e.context(format!("failed to fetch upcoming videos for {filter:?}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{
+        api::{DbChannel, VideoStatus},
+        pages::Format,
+        time::Rounding,
+    };
+
+    #[test]
+    fn page_link_round_trips() {
+        assert_eq!(page_link("/", &PageContext::default()), "/");
+
+        let ctx = PageContext {
+            kinds: vec![VideoKind::Placeholder],
+            statuses: vec![VideoStatus::Upcoming],
+            before: Some(TimeBound::In(86400)),
+            query: Some("karaoke & chill".to_string()),
+            cursor: Some(Cursor {
+                scheduled_time: "2026-01-01T12:00:00Z".to_string(),
+                id: "dQw4w9WgXcQ".to_string(),
+            }),
+            limit: 20,
+            tz: Tz::Asia__Tokyo,
+            locale: Locale::Ja,
+            precision: Precision { units: 2, rounding: Rounding::Round },
+            text: TextOptions { color: ColorMode::Never, width: Some(80), layout: Layout::Compact },
+            fields: vec!["title".to_string(), "url".to_string()],
+            ..Default::default()
+        };
+
+        let link = page_link("/", &ctx);
+        let query: RootQuery =
+            serde_urlencoded::from_str(link.strip_prefix("/?").unwrap()).unwrap();
+
+        assert_eq!(PageContext::try_from(query).unwrap(), ctx);
+    }
+
+    #[test]
+    fn page_link_keeps_format() {
+        let ctx = PageContext { format: Some(Format::Csv), ..Default::default() };
+        assert_eq!(page_link("/", &ctx), "/?format=csv");
+
+        let ctx = PageContext { limit: 20, format: Some(Format::Markdown), ..Default::default() };
+        assert_eq!(page_link("/", &ctx), "/?limit=20&format=markdown");
+    }
+
+    #[test]
+    fn pages_events_instead_of_videos() {
+        let video = |id: &str, scheduled_time: &str| DbVideo {
+            id: id.to_string(),
+            scheduled_time: scheduled_time.to_string(),
+            ..Default::default()
+        };
+        let event = |videos: Vec<DbVideo>| Event { videos };
+
+        // the simulcast of `a` is listed after `b` on its own
+        let events = vec![
+            event(vec![video("a", "2026-01-01T12:00:00Z"), video("c", "2026-01-01T12:10:00Z")]),
+            event(vec![video("b", "2026-01-01T12:05:00Z")]),
+            event(vec![video("d", "2026-01-01T13:00:00Z")]),
+            event(vec![video("e", "2026-01-01T14:00:00Z")]),
+        ];
+        let ids = |events: &[Event]| {
+            events.iter().map(|event| event.primary().id.clone()).collect::<Vec<_>>()
+        };
+
+        let (page, remaining) = page_events(events.clone(), None, 2);
+        assert_eq!(ids(&page), ["a", "b"]);
+        assert_eq!(remaining, 2);
+
+        let cursor = page.last().unwrap().cursor();
+        let (page, remaining) = page_events(events, Some(&cursor), 2);
+        assert_eq!(ids(&page), ["d", "e"]);
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn expands_groups_to_their_channels() {
+        sqlite::init_db();
+
+        let members = ["UCexpandfuri000000000000", "UCexpandmono000000000000"];
+        for id in members {
+            sqlite::upsert_db_channel(DbChannel {
+                id: id.to_string(),
+                name: id.to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        sqlite::replace_db_groups(&BTreeMap::from([(
+            "expand".to_string(),
+            members.map(String::from).to_vec(),
+        )]))
+        .unwrap();
+
+        let query = RootQuery { group: Some("expand".to_string()), ..Default::default() };
+        let ctx = PageContext::try_from(query).unwrap();
+        assert_eq!(get_channel_ids(&ctx).unwrap(), members);
+
+        let query = RootQuery { group: Some("missing".to_string()), ..Default::default() };
+        assert!(matches!(PageContext::try_from(query), Err(ApiError::NotFound(_))));
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        for query in [
+            "kind=live,stream",
+            "status=",
+            "before=soon",
+            "limit=0",
+            "limit=101",
+            "cursor=zz",
+            "tz=Mars/Olympus_Mons",
+            "lang=fr",
+            "precision=0",
+            "precision=2,up",
+            "color=rainbow",
+            "width=5",
+            "layout=grid",
+        ] {
+            let parsed: RootQuery = serde_urlencoded::from_str(query).unwrap();
+            assert!(
+                matches!(PageContext::try_from(parsed), Err(ApiError::BadRequest(_))),
+                "{query}"
+            );
+        }
+    }
+}
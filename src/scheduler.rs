@@ -1,69 +1,222 @@
 use anyhow::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use croner::{
+    Cron,
+    parser::{CronParser, Seconds},
+};
+use std::{collections::HashMap, sync::LazyLock, time::Instant};
 use tokio_cron_scheduler::{Job, JobScheduler};
 
 use crate::{
-    api::DbVideo,
+    api::{DbChannel, DbVideo},
     config::CONFIG,
-    pages, sqlite,
-    youtube::{self, videos},
+    metrics, oshi, pages,
+    platform::{Platform, Platforms, collab},
+    sqlite, status, youtube,
 };
 
+/// 30 seconds past every 14th, 29th, 44th and 59th minute
+const NEW_VIDEOS_SCHEDULE: &str = "30 14,29,44,59 * * * *";
+/// Every 5 minutes
+const EXISTING_VIDEOS_SCHEDULE: &str = "0 0/5 * * * *";
+/// 12am, every sunday
+const CHANNELS_SCHEDULE: &str = "0 0 0 * * 0";
+/// 6am, every day
+const WEBSUB_LEASES_SCHEDULE: &str = "0 0 6 * * *";
+/// Every minute, so a stopped scheduler is noticed quickly
+const HEARTBEAT_SCHEDULE: &str = "0 * * * * *";
+
+/// Schedules of the jobs that write videos
+static VIDEO_SCHEDULES: LazyLock<Vec<Cron>> = LazyLock::new(|| {
+    [NEW_VIDEOS_SCHEDULE, EXISTING_VIDEOS_SCHEDULE].iter().map(|s| parse_schedule(s)).collect()
+});
+
+/// Jobs the scheduler runs, websub leases only being renewed when websub is set up
+pub static JOBS: LazyLock<Vec<ScheduledJob>> = LazyLock::new(|| {
+    let mut jobs = vec![
+        ScheduledJob::new("new_videos", NEW_VIDEOS_SCHEDULE),
+        ScheduledJob::new("existing_videos", EXISTING_VIDEOS_SCHEDULE),
+        ScheduledJob::new("channels", CHANNELS_SCHEDULE),
+    ];
+
+    if CONFIG.websub.enabled() {
+        jobs.push(ScheduledJob::new("websub_leases", WEBSUB_LEASES_SCHEDULE));
+    }
+
+    jobs
+});
+
+/**
+ * Parses a schedule like the scheduler does, with seconds
+ */
+fn parse_schedule(schedule: &str) -> Cron {
+    CronParser::builder()
+        .seconds(Seconds::Required)
+        .dom_and_dow(true)
+        .build()
+        .parse(schedule)
+        .expect("invalid schedule")
+}
+
+/// A job by the name its metrics and status use
+pub struct ScheduledJob {
+    pub name: &'static str,
+    pub schedule: Cron,
+}
+
+impl ScheduledJob {
+    pub fn new(name: &'static str, schedule: &str) -> Self {
+        ScheduledJob { name, schedule: parse_schedule(schedule) }
+    }
+
+    pub fn next_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule.find_next_occurrence(&now, false).ok()
+    }
+
+    /**
+     * Time between the next two runs
+     */
+    pub fn interval(&self, now: DateTime<Utc>) -> Option<TimeDelta> {
+        let next = self.next_run(now)?;
+        let after = self.next_run(next)?;
+
+        Some(after - next)
+    }
+}
+
+/**
+ * When videos are next looked up, pages can be cached until then
+ */
+pub fn next_refresh(now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    VIDEO_SCHEDULES
+        .iter()
+        .filter_map(|schedule| schedule.find_next_occurrence(&now, false).ok())
+        .min()
+}
+
+/**
+ * Runs a scheduled job, recording how long it took and whether it failed
+ */
+async fn run_job(job: &'static str, task: impl Future<Output = Result<()>>) -> Result<()> {
+    let started_at = Utc::now();
+    let started = Instant::now();
+    let result = task.await;
+
+    metrics::record_job(job, started.elapsed().as_secs_f64(), result.is_ok());
+    status::record_job(job, started_at, result.as_ref().err().map(ToString::to_string));
+    result
+}
+
 pub async fn init_scheduler() -> Result<()> {
     let scheduler = JobScheduler::new().await?;
 
-    // run 30 seconds past every 14th, 29th, 44th and 59th minute
     // min quota usage: 96
     scheduler
-        .add(Job::new_async("30 14,29,44,59 * * * *", |_, _| {
+        .add(Job::new_async(NEW_VIDEOS_SCHEDULE, |_, _| {
             Box::pin(async {
-                if let Err(e) = check_new_videos().await {
+                if let Err(e) = run_job("new_videos", check_new_videos()).await {
                     tracing::error!("failed to check new videos: {e}");
                 }
             })
         })?)
         .await?;
 
-    // run every 5 minutes
     // min quota usage: 288
     scheduler
-        .add(Job::new_async("0 0/5 * * * *", |_, _| {
+        .add(Job::new_async(EXISTING_VIDEOS_SCHEDULE, |_, _| {
             Box::pin(async {
-                if let Err(e) = check_existing_videos().await {
+                if let Err(e) = run_job("existing_videos", check_existing_videos()).await {
                     tracing::error!("failed to update videos: {e}");
                 }
-
-                if let Err(e) = pages::refresh_page(pages::Pages::Root).await {
-                    tracing::error!("failed to refresh root page: {e}");
-                }
             })
         })?)
         .await?;
 
-    // 12am, every sunday
     scheduler
-        .add(Job::new_async("0 0 0 * * 0", |_, _| {
+        .add(Job::new_async(CHANNELS_SCHEDULE, |_, _| {
             Box::pin(async {
-                if let Err(e) = update_channels().await {
+                if let Err(e) = run_job("channels", update_channels()).await {
                     tracing::error!("failed to update channels: {e}");
                 }
             })
         })?)
         .await?;
 
+    // leases last 5 days by default, so renewing daily leaves plenty of slack
+    if CONFIG.websub.enabled() {
+        scheduler
+            .add(Job::new_async(WEBSUB_LEASES_SCHEDULE, |_, _| {
+                Box::pin(async {
+                    if let Err(e) = run_job("websub_leases", renew_websub_leases()).await {
+                        tracing::error!("failed to renew websub leases: {e}");
+                    }
+                })
+            })?)
+            .await?;
+    }
+
+    scheduler
+        .add(Job::new_async(HEARTBEAT_SCHEDULE, |_, _| {
+            Box::pin(async { status::record_heartbeat(Utc::now()) })
+        })?)
+        .await?;
+
     scheduler.start().await?;
+    status::record_start(Utc::now());
 
     let db_channel = sqlite::get_db_channels()?;
 
-    for (alias, id) in CONFIG.oshi.clone() {
-        if !db_channel.iter().any(|c| c.id == id) {
-            let api_channel = youtube::channels::get_channel_api(&id).await?;
+    for talent in oshi::get_talents() {
+        for channel in talent.channels {
+            if !db_channel.iter().any(|c| c.id == channel.id) {
+                let api_channel = match channel.platform.get_channel(&channel.id).await {
+                    Ok(api_channel) => api_channel,
+                    Err(e) => {
+                        tracing::error!("failed to fetch channel {}: {}", channel.id, e);
+                        continue;
+                    }
+                };
+
+                tracing::info!("adding {} ({}) to db", talent.name, api_channel.name);
+                sqlite::upsert_db_channel(api_channel)?;
+            }
+        }
+    }
+
+    let db_watched_channels = sqlite::get_db_watched_channels()?;
+
+    for input in &CONFIG.watched {
+        let channel = match oshi::resolve_channel(input).await {
+            Ok(channel) => channel,
+            Err(e) => {
+                tracing::error!("failed to resolve watched channel {}: {}", input, e);
+                continue;
+            }
+        };
+
+        if !db_watched_channels.iter().any(|c| c.id == channel.id) {
+            let api_channel = match channel.platform.get_channel(&channel.id).await {
+                Ok(api_channel) => api_channel,
+                Err(e) => {
+                    tracing::error!("failed to fetch watched channel {}: {}", input, e);
+                    continue;
+                }
+            };
 
-            tracing::info!("adding {} to db", alias);
-            sqlite::upsert_db_channel(api_channel.into())?;
+            tracing::info!("watching {} for collabs", api_channel.name);
+            sqlite::upsert_db_channel(DbChannel { watched: 1, ..api_channel })?;
         }
     }
 
-    pages::refresh_page(pages::Pages::List).await.unwrap();
+    pages::invalidate(pages::Change::Channels);
+
+    if CONFIG.websub.enabled() {
+        tokio::spawn(async {
+            if let Err(e) = renew_websub_leases().await {
+                tracing::error!("failed to subscribe to websub: {e}");
+            }
+        });
+    }
 
     Ok(())
 }
@@ -71,43 +224,102 @@ pub async fn init_scheduler() -> Result<()> {
 async fn check_new_videos() -> Result<()> {
     tracing::info!("checking for new videos");
 
-    let channel_ids = sqlite::get_db_channels()?;
-    let mut xml_video_ids = vec![];
+    let mut channel_ids = sqlite::get_db_channels()?;
+    channel_ids.extend(sqlite::get_db_watched_channels()?);
+
+    let mut platform_video_ids: HashMap<Platforms, Vec<String>> = HashMap::new();
 
     for channel in channel_ids {
         tracing::info!("checking channel {}", channel.name);
-        let video_ids = videos::get_video_ids_xml(&channel.id).await?;
+        let result = channel.platform.get_stream_ids(&channel.id).await;
+        status::record_feed(&channel, Utc::now(), result.as_ref().err().map(ToString::to_string));
+
+        let video_ids = match result {
+            Ok(video_ids) => video_ids,
+            Err(e) => {
+                tracing::error!("failed to list videos for channel {}: {}", channel.name, e);
+                continue;
+            }
+        };
 
         if video_ids.is_empty() {
-            tracing::info!("no videos found (xml) for channel {}", channel.name);
+            tracing::info!("no videos found ({}) for channel {}", channel.platform, channel.name);
             continue;
         }
 
-        tracing::info!("found {} videos (xml) for channel {}", video_ids.len(), channel.name);
-        xml_video_ids.extend(video_ids);
+        tracing::info!(
+            "found {} videos ({}) for channel {}",
+            video_ids.len(),
+            channel.platform,
+            channel.name
+        );
+        platform_video_ids.entry(channel.platform).or_default().extend(video_ids);
     }
 
-    if xml_video_ids.is_empty() {
-        tracing::info!("no videos found (xml)");
+    if platform_video_ids.is_empty() {
+        tracing::info!("no videos found");
         return Ok(());
     }
 
-    match youtube::videos::get_videos_api(&xml_video_ids).await {
+    for (platform, video_ids) in platform_video_ids {
+        upsert_platform_videos(platform, &video_ids).await?;
+    }
+
+    Ok(())
+}
+
+/**
+ * Looks up videos announced by a websub notification right away
+ */
+pub async fn check_notified_videos(video_ids: Vec<String>) -> Result<()> {
+    tracing::info!("checking {} notified videos", video_ids.len());
+
+    upsert_platform_videos(Platforms::Youtube, &video_ids).await
+}
+
+async fn upsert_platform_videos(platform: Platforms, video_ids: &[String]) -> Result<()> {
+    match platform.get_streams(video_ids).await {
         Err(e) => {
             tracing::error!("failed to fetch videos: {}", e);
         }
         Ok(api_videos) => {
+            let channels = sqlite::get_db_channels()?;
+            let api_videos: Vec<_> = api_videos
+                .into_iter()
+                .map(|video| {
+                    let participants = collab::get_participants(&video, &channels);
+                    (video, participants)
+                })
+                .filter(|(video, participants)| {
+                    // videos from watched channels are only kept for collabs
+                    !participants.is_empty()
+                        || channels.iter().any(|c| c.id == video.video.channel_id)
+                })
+                .collect();
+
             if api_videos.is_empty() {
                 tracing::info!("no videos found (api)");
                 return Ok(());
             }
 
-            tracing::info!("found {} videos (xml)", api_videos.len());
-            for api_video in &api_videos {
-                tracing::debug!("upserting {}", api_video.id);
+            tracing::info!("found {} videos (api)", api_videos.len());
+            for (api_video, participants) in &api_videos {
+                tracing::debug!("upserting {}", api_video.video.id);
+
+                if !participants.is_empty() {
+                    tracing::info!("found collab {} with {:?}", api_video.video.id, participants);
+                }
+            }
+
+            sqlite::upsert_db_videos(
+                api_videos.iter().map(|(video, _)| video.video.clone()).collect(),
+            )?;
+
+            for (api_video, participants) in &api_videos {
+                sqlite::upsert_db_participants(&api_video.video.id, participants)?;
             }
 
-            sqlite::upsert_db_videos(api_videos.iter().map(|video| video.into()).collect())?;
+            pages::invalidate(pages::Change::Videos);
         }
     };
 
@@ -117,19 +329,41 @@ async fn check_new_videos() -> Result<()> {
 async fn check_existing_videos() -> Result<()> {
     tracing::info!("checking for updated videos");
 
-    let db_videos = sqlite::get_db_upcoming_videos(&None)?;
+    let platform_videos = get_existing_videos()?;
 
-    if db_videos.is_empty() {
+    if platform_videos.is_empty() {
         tracing::info!("no videos found (db)");
         return Ok(());
     }
 
+    for (platform, db_videos) in platform_videos {
+        update_platform_videos(platform, db_videos).await?;
+    }
+
+    Ok(())
+}
+
+/**
+ * Every upcoming and live video by platform, the platforms fetch them in batches
+ */
+fn get_existing_videos() -> Result<HashMap<Platforms, Vec<DbVideo>>> {
+    let (db_videos, _) = sqlite::get_db_upcoming_videos(&sqlite::VideoFilter::default())?;
+
+    let mut platform_videos: HashMap<Platforms, Vec<DbVideo>> = HashMap::new();
+    for video in db_videos {
+        platform_videos.entry(video.platform).or_default().push(video);
+    }
+
+    Ok(platform_videos)
+}
+
+async fn update_platform_videos(platform: Platforms, db_videos: Vec<DbVideo>) -> Result<()> {
     let db_video_ids: Vec<String> = db_videos //
         .iter()
         .map(|video| video.id.clone())
         .collect();
 
-    match youtube::videos::get_videos_api(&db_video_ids).await {
+    match platform.get_streams(&db_video_ids).await {
         Err(e) => {
             tracing::error!("failed to fetch videos: {}", e);
             Ok(())
@@ -142,17 +376,19 @@ async fn check_existing_videos() -> Result<()> {
             } else if db_videos.len() == api_videos.len() {
                 tracing::info!("upserting {} videos (api)", api_videos.len());
                 for api_video in &api_videos {
-                    tracing::debug!("upserting {}", api_video.id);
+                    tracing::debug!("upserting {}", api_video.video.id);
                 }
 
-                sqlite::upsert_db_videos(api_videos.iter().map(|video| video.into()).collect())?;
+                sqlite::upsert_db_videos(
+                    api_videos.into_iter().map(|video| video.video).collect(),
+                )?;
             } else {
                 tracing::info!("cleaning up dangling videos");
 
                 let mut api_videos_iter = api_videos.iter();
                 let videos_to_delete: Vec<String> = db_video_ids
                     .into_iter()
-                    .filter(|video_id| !api_videos_iter.any(|v| &v.id == video_id))
+                    .filter(|video_id| !api_videos_iter.any(|v| &v.video.id == video_id))
                     .collect();
 
                 tracing::info!("deleting {} videos (api)", videos_to_delete.len());
@@ -175,6 +411,8 @@ async fn check_existing_videos() -> Result<()> {
                 sqlite::delete_db_videos(&videos_to_delete)?;
             }
 
+            pages::invalidate(pages::Change::Videos);
+
             Ok(())
         }
     }
@@ -193,17 +431,90 @@ async fn update_channels() -> Result<()> {
     for channel in channel_ids {
         tracing::info!("updating channel {}", channel.name);
 
-        match youtube::channels::get_channel_api(&channel.id).await {
+        match channel.platform.get_channel(&channel.id).await {
             Err(e) => {
                 tracing::error!("failed to fetch channel {}: {}", channel.name, e);
             }
             Ok(api_channel) => {
                 tracing::debug!("upserting channel {}", api_channel.id);
 
-                sqlite::upsert_db_channel(api_channel.into())?;
+                sqlite::upsert_db_channel(api_channel)?;
             }
         }
     }
 
+    pages::invalidate(pages::Change::Channels);
+
+    Ok(())
+}
+
+async fn renew_websub_leases() -> Result<()> {
+    tracing::info!("renewing websub leases");
+
+    let mut channels = sqlite::get_db_channels()?;
+    channels.extend(sqlite::get_db_watched_channels()?);
+
+    // websub is only offered for youtube feeds
+    for channel in channels.into_iter().filter(|c| c.platform == Platforms::Youtube) {
+        tracing::debug!("subscribing to channel {}", channel.name);
+
+        if let Err(e) = youtube::websub::subscribe(&channel.id).await {
+            tracing::error!("failed to subscribe to channel {}: {}", channel.name, e);
+        }
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_next_refresh() {
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+        assert_eq!(next_refresh(at("2026-01-01T12:00:00Z")), Some(at("2026-01-01T12:05:00Z")));
+        assert_eq!(next_refresh(at("2026-01-01T12:14:10Z")), Some(at("2026-01-01T12:14:30Z")));
+        assert_eq!(next_refresh(at("2026-01-01T12:14:30Z")), Some(at("2026-01-01T12:15:00Z")));
+        assert_eq!(next_refresh(at("2026-01-01T12:59:45Z")), Some(at("2026-01-01T13:00:00Z")));
+    }
+
+    #[test]
+    fn gets_every_existing_video() {
+        sqlite::init_db();
+
+        let channel_id = "UCexistingvideos00000000";
+        sqlite::upsert_db_channel(DbChannel {
+            id: channel_id.to_string(),
+            name: "Existing".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let video_ids: Vec<String> = (0..25).map(|i| format!("existing{i:03}")).collect();
+        let videos = video_ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| DbVideo {
+                id: id.clone(),
+                channel_id: channel_id.to_string(),
+                title: "karaoke".to_string(),
+                scheduled_time: format!("2026-01-01T12:{i:02}:00Z"),
+                // a few are live already
+                start_time: (i % 5 == 0).then(|| format!("2026-01-01T12:{i:02}:00Z")),
+                ..Default::default()
+            })
+            .collect();
+        sqlite::upsert_db_videos(videos).unwrap();
+
+        let existing = get_existing_videos().unwrap();
+        let found: Vec<&String> = existing[&Platforms::Youtube]
+            .iter()
+            .filter(|video| video.channel_id == channel_id)
+            .map(|video| &video.id)
+            .collect();
+
+        assert_eq!(found, video_ids.iter().collect::<Vec<_>>());
+    }
+}
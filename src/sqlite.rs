@@ -1,26 +1,61 @@
 use anyhow::Result;
-use rusqlite::{Connection, params};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
 use std::{
+    collections::{BTreeMap, HashMap},
     fs,
-    sync::{LazyLock, Mutex},
+    sync::{LazyLock, Mutex, MutexGuard},
+    time::Instant,
 };
 
-use crate::api::{DbChannel, DbVideo};
+use crate::{
+    api::{Cursor, DbChannel, DbVideo, VideoKind, VideoStatus},
+    error::ApiError,
+    metrics,
+};
 
 static DB: LazyLock<Mutex<Connection>> = LazyLock::new(|| {
+    // tests share a throwaway database instead of the data dir
+    if cfg!(test) {
+        return Mutex::new(Connection::open_in_memory().expect("failed to open db"));
+    }
+
     fs::create_dir_all("data").expect("failed to create data dir");
     let conn = Connection::open("data/db.sqlite").expect("failed to open db");
     Mutex::new(conn)
 });
 
+/**
+ * Waits for the connection, which a single request or job holds at a time,
+ * failing once a panic left it poisoned
+ */
+fn lock_db() -> Result<MutexGuard<'static, Connection>> {
+    let started = Instant::now();
+    let conn = DB.lock().map_err(|e| {
+        tracing::error!("failed to lock DB: {e}");
+        ApiError::Unavailable
+    })?;
+    metrics::record_db_lock_wait(started.elapsed().as_secs_f64());
+
+    Ok(conn)
+}
+
 pub fn init_db() {
-    let conn = DB.lock().expect("failed to lock DB");
+    let conn = lock_db().expect("failed to lock DB");
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS channels (
 			id TEXT PRIMARY KEY,
 			name TEXT NOT NULL,
-			disabled INTEGER NOT NULL DEFAULT 0
+			platform TEXT NOT NULL DEFAULT 'youtube',
+			handle TEXT,
+			description TEXT,
+			country TEXT,
+			thumbnails TEXT NOT NULL DEFAULT '{}',
+			banner TEXT,
+			subscriber_count INTEGER,
+			disabled INTEGER NOT NULL DEFAULT 0,
+			watched INTEGER NOT NULL DEFAULT 0
 		)",
         [],
     )
@@ -30,7 +65,15 @@ pub fn init_db() {
         "CREATE TABLE IF NOT EXISTS videos (
 			id TEXT PRIMARY KEY,
 			channel_id TEXT NOT NULL,
+			platform TEXT NOT NULL DEFAULT 'youtube',
 			title TEXT NOT NULL,
+			description TEXT,
+			thumbnails TEXT NOT NULL DEFAULT '{}',
+			live_broadcast_content TEXT NOT NULL DEFAULT 'none',
+			concurrent_viewers INTEGER,
+			duration INTEGER,
+			premiere INTEGER NOT NULL DEFAULT 0,
+			kind TEXT NOT NULL DEFAULT 'live',
 			scheduled_time TEXT NOT NULL,
 			start_time TEXT,
 			end_time TEXT,
@@ -39,23 +82,134 @@ pub fn init_db() {
         [],
     )
     .expect("failed to create video table");
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS video_participants (
+			video_id TEXT NOT NULL,
+			channel_id TEXT NOT NULL,
+			PRIMARY KEY (video_id, channel_id),
+			FOREIGN KEY (video_id) REFERENCES videos(id),
+			FOREIGN KEY (channel_id) REFERENCES channels(id)
+		)",
+        [],
+    )
+    .expect("failed to create video participant table");
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS video_groups (
+			video_id TEXT PRIMARY KEY,
+			group_id TEXT
+		)",
+        [],
+    )
+    .expect("failed to create video group table");
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS channel_refs (
+			ref TEXT PRIMARY KEY,
+			channel_id TEXT NOT NULL
+		)",
+        [],
+    )
+    .expect("failed to create channel ref table");
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS channel_groups (
+			group_name TEXT NOT NULL,
+			channel_id TEXT NOT NULL,
+			PRIMARY KEY (group_name, channel_id),
+			FOREIGN KEY (channel_id) REFERENCES channels(id)
+		)",
+        [],
+    )
+    .expect("failed to create channel group table");
+
+    // columns added after the initial schema
+    for (table, column, definition) in [
+        ("channels", "watched", "INTEGER NOT NULL DEFAULT 0"),
+        ("channels", "handle", "TEXT"),
+        ("channels", "description", "TEXT"),
+        ("channels", "country", "TEXT"),
+        ("channels", "thumbnails", "TEXT NOT NULL DEFAULT '{}'"),
+        ("channels", "banner", "TEXT"),
+        ("channels", "subscriber_count", "INTEGER"),
+        ("channels", "platform", "TEXT NOT NULL DEFAULT 'youtube'"),
+        ("videos", "platform", "TEXT NOT NULL DEFAULT 'youtube'"),
+        ("videos", "description", "TEXT"),
+        ("videos", "thumbnails", "TEXT NOT NULL DEFAULT '{}'"),
+        ("videos", "live_broadcast_content", "TEXT NOT NULL DEFAULT 'none'"),
+        ("videos", "concurrent_viewers", "INTEGER"),
+        ("videos", "duration", "INTEGER"),
+        ("videos", "premiere", "INTEGER NOT NULL DEFAULT 0"),
+        ("videos", "kind", "TEXT NOT NULL DEFAULT 'live'"),
+    ] {
+        add_column(&conn, table, column, definition).expect("failed to migrate db");
+    }
+}
+
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, rusqlite::Error>>()?
+        .iter()
+        .any(|name| name == column);
+
+    if !exists {
+        tracing::info!("adding column {table}.{column}");
+        conn.execute(&format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"), [])?;
+    }
+
+    Ok(())
 }
 
 /* Channels */
 
+/**
+ * Reads from the db file, failing when it is missing, locked or corrupt
+ */
+pub fn ping_db() -> Result<()> {
+    let conn = lock_db()?;
+    conn.query_row("SELECT COUNT(*) FROM channels", [], |row| row.get::<_, i64>(0))?;
+
+    Ok(())
+}
+
 pub fn get_db_channels() -> Result<Vec<DbChannel>> {
-    let conn = DB.lock().expect("failed to lock DB");
+    get_db_channels_where(false)
+}
+
+/**
+ * Channels that are only scanned for collabs with the tracked ones
+ */
+pub fn get_db_watched_channels() -> Result<Vec<DbChannel>> {
+    get_db_channels_where(true)
+}
+
+fn get_db_channels_where(watched: bool) -> Result<Vec<DbChannel>> {
+    let conn = lock_db()?;
 
     let mut stmt = conn.prepare(
-        "SELECT id,name,disabled FROM channels
-		WHERE disabled = 0",
+        "SELECT
+			id,name,disabled,watched,handle,description,country,thumbnails,banner,subscriber_count,
+			platform
+		FROM channels
+		WHERE disabled = 0 AND watched = ?1",
     )?;
 
-    let channel_iter = stmt.query_map([], |row| {
+    let channel_iter = stmt.query_map([watched], |row| {
         Ok(DbChannel {
             id: row.get(0)?, //
             name: row.get(1)?,
             disabled: row.get(2)?,
+            watched: row.get(3)?,
+            handle: row.get(4)?,
+            description: row.get(5)?,
+            country: row.get(6)?,
+            thumbnails: serde_json::from_str(&row.get::<_, String>(7)?).unwrap_or_default(),
+            banner: row.get(8)?,
+            subscriber_count: row.get(9)?,
+            platform: row.get::<_, String>(10)?.parse().unwrap_or_default(),
         })
     })?;
 
@@ -65,68 +219,306 @@ pub fn get_db_channels() -> Result<Vec<DbChannel>> {
 }
 
 pub fn upsert_db_channel(channel: DbChannel) -> Result<()> {
-    let mut conn = DB.lock().expect("failed to lock DB");
+    let mut conn = lock_db()?;
     let tx = conn.transaction()?;
 
     tx.execute(
-        "INSERT OR REPLACE INTO channels (id,name,disabled)
-				VALUES (?1,?2,?3)",
-        params![channel.id, channel.name, channel.disabled],
+        "INSERT OR REPLACE INTO channels (
+					id,name,disabled,watched,
+					handle,description,country,thumbnails,banner,subscriber_count,platform
+				)
+				VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11)",
+        params![
+            channel.id,
+            channel.name,
+            channel.disabled,
+            channel.watched,
+            channel.handle,
+            channel.description,
+            channel.country,
+            serde_json::to_string(&channel.thumbnails)?,
+            channel.banner,
+            channel.subscriber_count,
+            channel.platform.as_str()
+        ],
     )?;
 
     tx.commit()?;
     Ok(())
 }
 
+/**
+ * Stops tracking a channel, returning whether it was tracked
+ */
+pub fn disable_db_channel(channel_id: &str) -> Result<bool> {
+    let conn = lock_db()?;
+
+    let updated = conn
+        .execute("UPDATE channels SET disabled = 1 WHERE id = ?1 AND disabled = 0", [channel_id])?;
+
+    Ok(updated > 0)
+}
+
+/* Channel refs */
+
+pub fn get_db_channel_ref(channel_ref: &str) -> Result<Option<String>> {
+    let conn = lock_db()?;
+
+    let channel_id = conn
+        .query_row("SELECT channel_id FROM channel_refs WHERE ref = ?1", [channel_ref], |row| {
+            row.get(0)
+        })
+        .optional()?;
+
+    Ok(channel_id)
+}
+
+pub fn upsert_db_channel_ref(channel_ref: &str, channel_id: &str) -> Result<()> {
+    let conn = lock_db()?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO channel_refs (ref,channel_id)
+				VALUES (?1,?2)",
+        params![channel_ref, channel_id],
+    )?;
+
+    Ok(())
+}
+
+/* Channel groups */
+
+/**
+ * Groups mapped to the ids of their member channels
+ */
+pub fn get_db_groups() -> Result<BTreeMap<String, Vec<String>>> {
+    let conn = lock_db()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT group_name,channel_id FROM channel_groups
+			ORDER BY group_name,channel_id",
+    )?;
+    let member_iter =
+        stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for member in member_iter {
+        let (group_name, channel_id) = member?;
+        groups.entry(group_name).or_default().push(channel_id);
+    }
+
+    Ok(groups)
+}
+
+/**
+ * Replaces every group, groups are defined in config so stale ones are dropped
+ */
+pub fn replace_db_groups(groups: &BTreeMap<String, Vec<String>>) -> Result<()> {
+    let mut conn = lock_db()?;
+    let tx = conn.transaction()?;
+
+    tx.execute("DELETE FROM channel_groups", [])?;
+
+    for (group_name, channel_ids) in groups {
+        for channel_id in channel_ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO channel_groups (group_name,channel_id)
+					VALUES (?1,?2)",
+                params![group_name, channel_id],
+            )?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
 /* Videos */
 
-pub fn get_db_upcoming_videos(channel_id: &Option<String>) -> Result<Vec<DbVideo>> {
-    let conn = DB.lock().expect("failed to lock DB");
+/// Filters for upcoming videos, empty lists match everything
+#[derive(Debug, Default)]
+pub struct VideoFilter {
+    /// Channels whose videos and collabs are listed, every tracked channel when empty
+    pub channel_ids: Vec<String>,
+    pub kinds: Vec<VideoKind>,
+    pub statuses: Vec<VideoStatus>,
+    /// Start, or scheduled start, bounds
+    pub before: Option<DateTime<Utc>>,
+    pub after: Option<DateTime<Utc>>,
+    /// Case-insensitive title search
+    pub title: Option<String>,
+    /// Continues a previous listing
+    pub cursor: Option<Cursor>,
+    pub limit: Option<usize>,
+}
+
+/**
+ * Upcoming videos along with how many matched the filter, ignoring the limit
+ */
+pub fn get_db_upcoming_videos(filter: &VideoFilter) -> Result<(Vec<DbVideo>, usize)> {
+    let conn = lock_db()?;
 
     let mut stmt = conn.prepare(
         "SELECT
 				v.id,v.channel_id,v.title,v.scheduled_time,v.start_time,v.end_time,
-				c.name
+				c.name,
+				v.description,v.thumbnails,v.live_broadcast_content,v.concurrent_viewers,
+				v.duration,v.premiere,v.kind,
+				CASE WHEN json_array_length(?1) = 0 THEN c.watched
+					ELSE v.channel_id NOT IN (SELECT value FROM json_each(?1)) END,
+				v.platform,c.handle,
+				COUNT(*) OVER ()
 			FROM videos v
 			    INNER JOIN channels c ON v.channel_id = c.id
 			WHERE
-				v.end_time is null AND (?1 IS NULL OR v.channel_id = ?1)
-			ORDER BY v.scheduled_time ASC
-			LIMIT 10",
+				v.end_time is null
+				AND (
+					(json_array_length(?1) = 0 AND c.watched = 0)
+					OR v.channel_id IN (SELECT value FROM json_each(?1))
+					OR v.id IN (
+						SELECT p.video_id FROM video_participants p
+						WHERE json_array_length(?1) = 0
+							OR p.channel_id IN (SELECT value FROM json_each(?1))
+					)
+				)
+				AND (json_array_length(?2) = 0 OR v.kind IN (SELECT value FROM json_each(?2)))
+				AND (
+					json_array_length(?3) = 0
+					OR CASE WHEN v.start_time IS NULL THEN 'upcoming' ELSE 'live' END
+						IN (SELECT value FROM json_each(?3))
+				)
+				AND (?4 IS NULL OR unixepoch(COALESCE(v.start_time, v.scheduled_time)) < ?4)
+				AND (?5 IS NULL OR unixepoch(COALESCE(v.start_time, v.scheduled_time)) > ?5)
+				AND (?6 IS NULL OR instr(lower(v.title), lower(?6)) > 0)
+				AND (?8 IS NULL OR (v.scheduled_time, v.id) > (?8, ?9))
+			ORDER BY v.scheduled_time ASC, v.id ASC
+			LIMIT ?7",
     )?;
 
-    let video_iter = stmt.query_map([channel_id], |row| {
-        Ok(DbVideo {
-            id: row.get(0)?,
-            channel_id: row.get(1)?,
-            channel_name: row.get(6)?,
-            title: row.get(2)?,
-            scheduled_time: row.get(3)?,
-            start_time: row.get(4)?,
-            end_time: row.get(5)?,
-        })
-    })?;
+    let channel_ids = serde_json::to_string(&filter.channel_ids)?;
+    let kinds = serde_json::to_string(&filter.kinds)?;
+    let statuses = serde_json::to_string(&filter.statuses)?;
+    // a negative limit disables it
+    let limit = filter.limit.map_or(-1, |limit| limit as i64);
+
+    let video_iter = stmt.query_map(
+        params![
+            channel_ids,
+            kinds,
+            statuses,
+            filter.before.map(|time| time.timestamp()),
+            filter.after.map(|time| time.timestamp()),
+            filter.title,
+            limit,
+            filter.cursor.as_ref().map(|cursor| &cursor.scheduled_time),
+            filter.cursor.as_ref().map(|cursor| &cursor.id),
+        ],
+        |row| Ok((row_to_video(row)?, row.get::<_, i64>(17)?)),
+    )?;
+
+    let rows = video_iter.collect::<Result<Vec<_>, rusqlite::Error>>()?;
+    let total = rows.first().map_or(0, |(_, total)| *total as usize);
+
+    Ok((rows.into_iter().map(|(video, _)| video).collect(), total))
+}
+
+/**
+ * How many stored videos are upcoming, live or ended
+ */
+pub fn count_db_videos() -> Result<HashMap<String, i64>> {
+    let conn = lock_db()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT
+				CASE WHEN end_time IS NOT NULL THEN 'ended'
+					WHEN start_time IS NOT NULL THEN 'live'
+					ELSE 'upcoming' END AS status,
+				COUNT(*)
+			FROM videos
+			GROUP BY status",
+    )?;
+
+    let counts = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<HashMap<_, _>, rusqlite::Error>>()?;
+
+    Ok(counts)
+}
 
-    let videos = video_iter.collect::<Result<Vec<_>, rusqlite::Error>>()?;
+pub fn get_db_video(video_id: &str) -> Result<Option<DbVideo>> {
+    let conn = lock_db()?;
 
-    Ok(videos)
+    let video = conn
+        .query_row(
+            "SELECT
+					v.id,v.channel_id,v.title,v.scheduled_time,v.start_time,v.end_time,
+					c.name,
+					v.description,v.thumbnails,v.live_broadcast_content,v.concurrent_viewers,
+					v.duration,v.premiere,v.kind,
+					c.watched,
+					v.platform,c.handle
+				FROM videos v
+				    INNER JOIN channels c ON v.channel_id = c.id
+				WHERE v.id = ?1",
+            [video_id],
+            row_to_video,
+        )
+        .optional()?;
+
+    Ok(video)
+}
+
+/**
+ * Reads the video columns shared by the video queries
+ */
+fn row_to_video(row: &rusqlite::Row) -> rusqlite::Result<DbVideo> {
+    Ok(DbVideo {
+        id: row.get(0)?,
+        channel_id: row.get(1)?,
+        channel_name: row.get(6)?,
+        title: row.get(2)?,
+        description: row.get(7)?,
+        thumbnails: serde_json::from_str(&row.get::<_, String>(8)?).unwrap_or_default(),
+        live_broadcast_content: row.get(9)?,
+        concurrent_viewers: row.get(10)?,
+        duration: row.get(11)?,
+        premiere: row.get(12)?,
+        kind: row.get::<_, String>(13)?.parse().unwrap_or_default(),
+        collab: row.get(14)?,
+        platform: row.get::<_, String>(15)?.parse().unwrap_or_default(),
+        channel_handle: row.get(16)?,
+        scheduled_time: row.get(3)?,
+        start_time: row.get(4)?,
+        end_time: row.get(5)?,
+    })
 }
 
 pub fn upsert_db_videos(videos: Vec<DbVideo>) -> Result<()> {
-    let mut conn = DB.lock().expect("failed to lock DB");
+    let mut conn = lock_db()?;
     let tx = conn.transaction()?;
 
     for video in videos {
         tx.execute(
-            "INSERT OR REPLACE INTO videos (id,channel_id,title,scheduled_time,start_time,end_time)
-				VALUES (?1,?2,?3,?4,?5,?6)",
+            "INSERT OR REPLACE INTO videos (
+					id,channel_id,title,scheduled_time,start_time,end_time,
+					description,thumbnails,live_broadcast_content,concurrent_viewers,duration,premiere,
+					kind,platform
+				)
+				VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14)",
             params![
                 video.id,
                 video.channel_id,
                 video.title,
                 video.scheduled_time,
                 video.start_time,
-                video.end_time
+                video.end_time,
+                video.description,
+                serde_json::to_string(&video.thumbnails)?,
+                video.live_broadcast_content,
+                video.concurrent_viewers,
+                video.duration,
+                video.premiere,
+                video.kind.as_str(),
+                video.platform.as_str()
             ],
         )?;
     }
@@ -136,13 +528,81 @@ pub fn upsert_db_videos(videos: Vec<DbVideo>) -> Result<()> {
 }
 
 pub fn delete_db_videos(videos: &Vec<String>) -> Result<()> {
-    let mut conn = DB.lock().expect("failed to lock DB");
+    let mut conn = lock_db()?;
     let tx = conn.transaction()?;
 
     for video_id in videos {
+        tx.execute("DELETE FROM video_participants WHERE video_id = ?1", params![video_id])?;
+        tx.execute("DELETE FROM video_groups WHERE video_id = ?1", params![video_id])?;
         tx.execute("DELETE FROM videos WHERE id = ?1", params![video_id])?;
     }
 
     tx.commit()?;
     Ok(())
 }
+
+/* Participants */
+
+pub fn upsert_db_participants(video_id: &str, channel_ids: &[String]) -> Result<()> {
+    let mut conn = lock_db()?;
+    let tx = conn.transaction()?;
+
+    tx.execute("DELETE FROM video_participants WHERE video_id = ?1", params![video_id])?;
+
+    for channel_id in channel_ids {
+        tx.execute(
+            "INSERT OR IGNORE INTO video_participants (video_id,channel_id)
+				VALUES (?1,?2)",
+            params![video_id, channel_id],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+/* Video groups */
+
+/**
+ * Manual simulcast groups, a `None` group keeps the video out of automatic grouping
+ */
+pub fn get_db_video_groups() -> Result<HashMap<String, Option<String>>> {
+    let conn = lock_db()?;
+
+    let mut stmt = conn.prepare("SELECT video_id,group_id FROM video_groups")?;
+    let group_iter = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    let groups = group_iter.collect::<Result<HashMap<_, _>, rusqlite::Error>>()?;
+
+    Ok(groups)
+}
+
+/**
+ * Links videos into one group, or unlinks them when no group is given.
+ * Linking first removes the groups the videos were in, so groups never overlap
+ */
+pub fn upsert_db_video_groups(video_ids: &[String], group_id: Option<&str>) -> Result<()> {
+    let mut conn = lock_db()?;
+    let tx = conn.transaction()?;
+
+    if group_id.is_some() {
+        for video_id in video_ids {
+            tx.execute(
+                "DELETE FROM video_groups
+					WHERE group_id = (SELECT group_id FROM video_groups WHERE video_id = ?1)",
+                params![video_id],
+            )?;
+        }
+    }
+
+    for video_id in video_ids {
+        tx.execute(
+            "INSERT OR REPLACE INTO video_groups (video_id,group_id)
+				VALUES (?1,?2)",
+            params![video_id, group_id],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
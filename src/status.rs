@@ -0,0 +1,391 @@
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{LazyLock, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    api::DbChannel,
+    colors::{ColorMode, Colorize, Role},
+    config::CONFIG,
+    scheduler::{self, ScheduledJob},
+    sqlite,
+};
+
+/// How long the scheduler can go without a heartbeat, it beats every minute
+const HEARTBEAT_GRACE: TimeDelta = TimeDelta::minutes(3);
+
+static STATE: LazyLock<Mutex<State>> = LazyLock::new(|| Mutex::new(State::default()));
+
+/// What the scheduler did since the server started
+#[derive(Debug, Default)]
+struct State {
+    /// When the scheduler started, jobs that never succeeded are measured from it
+    started: Option<DateTime<Utc>>,
+    heartbeat: Option<DateTime<Utc>>,
+    jobs: BTreeMap<&'static str, Runs>,
+    /// Feeds by channel id, with the channel name
+    feeds: BTreeMap<String, (String, Runs)>,
+}
+
+/// Outcomes of a job or feed, the last error being kept after later successes
+#[derive(Clone, Debug, Default)]
+struct Runs {
+    last_run: Option<DateTime<Utc>>,
+    last_success: Option<DateTime<Utc>>,
+    last_error: Option<(DateTime<Utc>, String)>,
+}
+
+impl Runs {
+    fn record(&mut self, at: DateTime<Utc>, error: Option<String>) {
+        self.last_run = Some(at);
+
+        match error {
+            Some(error) => self.last_error = Some((at, error)),
+            None => self.last_success = Some(at),
+        }
+    }
+}
+
+/**
+ * Keeps reporting after a panic while recording, the state stays usable
+ */
+fn lock_state() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub fn record_start(at: DateTime<Utc>) {
+    let mut state = lock_state();
+    state.started = Some(at);
+    state.heartbeat = Some(at);
+}
+
+pub fn record_heartbeat(at: DateTime<Utc>) {
+    lock_state().heartbeat = Some(at);
+}
+
+pub fn record_job(job: &'static str, at: DateTime<Utc>, error: Option<String>) {
+    lock_state().jobs.entry(job).or_default().record(at, error);
+}
+
+pub fn record_feed(channel: &DbChannel, at: DateTime<Utc>, error: Option<String>) {
+    let mut state = lock_state();
+    let (name, runs) = state.feeds.entry(channel.id.clone()).or_default();
+
+    name.clone_from(&channel.name);
+    runs.record(at, error);
+}
+
+/// A check of liveness or readiness, with the reason it failed
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: Option<String>,
+}
+
+impl Check {
+    fn passed(name: &str) -> Self {
+        Check { name: name.to_string(), ok: true, detail: None }
+    }
+
+    fn failed(name: &str, detail: String) -> Self {
+        Check { name: name.to_string(), ok: false, detail: Some(detail) }
+    }
+}
+
+fn check_db(ping: Result<()>) -> Check {
+    match ping {
+        Ok(()) => Check::passed("db"),
+        Err(e) => Check::failed("db", e.to_string()),
+    }
+}
+
+fn check_scheduler(state: &State, now: DateTime<Utc>) -> Check {
+    match state.heartbeat {
+        None => Check::failed("scheduler", "not started".to_string()),
+        Some(heartbeat) if now - heartbeat > HEARTBEAT_GRACE => {
+            Check::failed("scheduler", format!("no heartbeat since {}", format_time(heartbeat)))
+        }
+        Some(_) => Check::passed("scheduler"),
+    }
+}
+
+/**
+ * Fails once a job missed `stale_runs` runs in a row, counting from startup when it never succeeded
+ */
+fn check_job(job: &ScheduledJob, state: &State, now: DateTime<Utc>, stale_runs: i32) -> Check {
+    let last_success = state.jobs.get(job.name).and_then(|runs| runs.last_success);
+
+    let (Some(since), Some(interval)) = (last_success.or(state.started), job.interval(now)) else {
+        return Check::failed(job.name, "never ran".to_string());
+    };
+
+    if now - since <= interval * stale_runs {
+        return Check::passed(job.name);
+    }
+
+    match last_success {
+        Some(at) => Check::failed(job.name, format!("last succeeded at {}", format_time(at))),
+        None => Check::failed(job.name, "never succeeded".to_string()),
+    }
+}
+
+/**
+ * Whether the server should be restarted, only a stopped scheduler calls for it
+ */
+pub fn liveness(now: DateTime<Utc>) -> Vec<Check> {
+    vec![check_scheduler(&lock_state(), now)]
+}
+
+/**
+ * Whether the server can answer with fresh data
+ */
+pub fn readiness(now: DateTime<Utc>) -> Vec<Check> {
+    // a locked or missing db fails its check instead of the probe
+    let db = check_db(sqlite::ping_db());
+
+    readiness_checks(db, &lock_state(), now, CONFIG.health.stale_runs)
+}
+
+fn readiness_checks(db: Check, state: &State, now: DateTime<Utc>, stale_runs: i32) -> Vec<Check> {
+    let mut checks = vec![db, check_scheduler(state, now)];
+    checks.extend(scheduler::JOBS.iter().map(|job| check_job(job, state, now, stale_runs)));
+
+    checks
+}
+
+/**
+ * A line per check, for health probes and terminals
+ */
+pub fn format_checks(checks: &[Check], color: ColorMode) -> String {
+    let width = checks.iter().map(|check| check.name.len()).max().unwrap_or_default();
+
+    checks
+        .iter()
+        .map(|check| {
+            let result = match &check.detail {
+                None => "ok".paint(Role::Healthy, color),
+                Some(detail) => format!("{} {detail}", "failed:".paint(Role::Error, color)),
+            };
+
+            format!("{:width$}  {result}\n", check.name)
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct JobStatus {
+    name: &'static str,
+    last_run: Option<String>,
+    last_success: Option<String>,
+    next_run: Option<String>,
+    last_error: Option<ErrorStatus>,
+}
+
+#[derive(Serialize)]
+struct ChannelStatus {
+    id: String,
+    name: String,
+    last_fetch: Option<String>,
+    last_success: Option<String>,
+    last_error: Option<ErrorStatus>,
+}
+
+#[derive(Serialize)]
+struct ErrorStatus {
+    at: String,
+    message: String,
+}
+
+/// Readiness checks with what each job and feed last did
+#[derive(Serialize)]
+pub struct Status {
+    ready: bool,
+    checks: Vec<Check>,
+    jobs: Vec<JobStatus>,
+    /// Feeds fetched since startup
+    channels: Vec<ChannelStatus>,
+}
+
+impl Status {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        let checks = readiness(now);
+        let state = lock_state();
+
+        let jobs = scheduler::JOBS
+            .iter()
+            .map(|job| {
+                let runs = state.jobs.get(job.name).cloned().unwrap_or_default();
+
+                JobStatus {
+                    name: job.name,
+                    last_run: runs.last_run.map(format_time),
+                    last_success: runs.last_success.map(format_time),
+                    next_run: job.next_run(now).map(format_time),
+                    last_error: runs.last_error.map(ErrorStatus::from),
+                }
+            })
+            .collect();
+
+        let channels = state
+            .feeds
+            .iter()
+            .map(|(id, (name, runs))| ChannelStatus {
+                id: id.clone(),
+                name: name.clone(),
+                last_fetch: runs.last_run.map(format_time),
+                last_success: runs.last_success.map(format_time),
+                last_error: runs.last_error.clone().map(ErrorStatus::from),
+            })
+            .collect();
+
+        Status { ready: checks.iter().all(|check| check.ok), checks, jobs, channels }
+    }
+
+    pub fn to_text(&self, color: ColorMode) -> String {
+        let mut text = format_checks(&self.checks, color);
+
+        for job in &self.jobs {
+            text.push_str(&format!("\n{}\n", job.name.paint(Role::Title, color)));
+            text.push_str(&field("last run", job.last_run.as_deref()));
+            text.push_str(&field("last success", job.last_success.as_deref()));
+            text.push_str(&field("next run", job.next_run.as_deref()));
+            if let Some(error) = &job.last_error {
+                text.push_str(&error.to_text(color));
+            }
+        }
+
+        for channel in &self.channels {
+            text.push_str(&format!(
+                "\n{} ({})\n",
+                channel.name.paint(Role::Title, color),
+                channel.id
+            ));
+            text.push_str(&field("last fetch", channel.last_fetch.as_deref()));
+            text.push_str(&field("last success", channel.last_success.as_deref()));
+            if let Some(error) = &channel.last_error {
+                text.push_str(&error.to_text(color));
+            }
+        }
+
+        text
+    }
+}
+
+impl From<(DateTime<Utc>, String)> for ErrorStatus {
+    fn from((at, message): (DateTime<Utc>, String)) -> Self {
+        ErrorStatus { at: format_time(at), message }
+    }
+}
+
+impl ErrorStatus {
+    fn to_text(&self, color: ColorMode) -> String {
+        let message = self.message.paint(Role::Error, color);
+        field("last error", Some(&format!("{message} ({})", self.at)))
+    }
+}
+
+fn field(label: &str, value: Option<&str>) -> String {
+    format!("  {:14}{}\n", format!("{label}:"), value.unwrap_or("never"))
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ApiError;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn checks_staleness() {
+        // every 5 minutes, stale after 3 missed runs
+        let job = ScheduledJob::new("existing_videos", "0 0/5 * * * *");
+        let now = at("2026-01-01T12:00:00Z");
+
+        let mut state = State::default();
+        assert_eq!(
+            check_job(&job, &state, now, 3),
+            Check::failed("existing_videos", "never ran".into())
+        );
+        assert!(!check_scheduler(&state, now).ok);
+
+        state.started = Some(at("2026-01-01T11:50:00Z"));
+        state.heartbeat = Some(at("2026-01-01T11:59:00Z"));
+        assert!(check_job(&job, &state, now, 3).ok);
+        assert!(check_scheduler(&state, now).ok);
+
+        state.started = Some(at("2026-01-01T11:00:00Z"));
+        state.heartbeat = Some(at("2026-01-01T11:55:00Z"));
+        assert_eq!(
+            check_job(&job, &state, now, 3),
+            Check::failed("existing_videos", "never succeeded".into())
+        );
+        assert_eq!(
+            check_scheduler(&state, now),
+            Check::failed("scheduler", "no heartbeat since 2026-01-01T11:55:00Z".into())
+        );
+
+        let runs = state.jobs.entry("existing_videos").or_default();
+        runs.record(at("2026-01-01T11:40:00Z"), None);
+        runs.record(at("2026-01-01T11:45:00Z"), Some("quota exceeded".into()));
+        assert_eq!(
+            check_job(&job, &state, now, 3),
+            Check::failed("existing_videos", "last succeeded at 2026-01-01T11:40:00Z".into())
+        );
+        assert!(check_job(&job, &state, now, 4).ok);
+    }
+
+    #[test]
+    fn fails_readiness() {
+        let now = at("2026-01-01T12:00:00Z");
+        let mut state = State {
+            started: Some(at("2026-01-01T11:00:00Z")),
+            heartbeat: Some(at("2026-01-01T11:59:00Z")),
+            ..Default::default()
+        };
+        for job in scheduler::JOBS.iter() {
+            state.jobs.entry(job.name).or_default().record(at("2026-01-01T11:59:30Z"), None);
+        }
+
+        let ready = |checks: &[Check]| checks.iter().all(|check| check.ok);
+        assert!(ready(&readiness_checks(check_db(Ok(())), &state, now, 3)));
+
+        let down = check_db(Err(ApiError::Unavailable.into()));
+        let checks = readiness_checks(down, &state, now, 3);
+        assert!(!ready(&checks));
+        assert_eq!(
+            checks[0],
+            Check::failed("db", "the database is unavailable, try again later".into())
+        );
+
+        // every 5 minutes, last succeeded an hour ago
+        let mut runs = Runs::default();
+        runs.record(at("2026-01-01T11:00:00Z"), None);
+        state.jobs.insert("existing_videos", runs);
+        let checks = readiness_checks(check_db(Ok(())), &state, now, 3);
+        let failed: Vec<&Check> = checks.iter().filter(|check| !check.ok).collect();
+        assert_eq!(
+            failed,
+            [&Check::failed("existing_videos", "last succeeded at 2026-01-01T11:00:00Z".into())]
+        );
+    }
+
+    #[test]
+    fn formats_checks() {
+        let checks = [Check::passed("db"), Check::failed("scheduler", "not started".into())];
+
+        assert_eq!(
+            format_checks(&checks, ColorMode::Never),
+            "db         ok\nscheduler  failed: not started\n"
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use axum::Router;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+/**
+ * Serves a router on a free local port for the rest of the test, for clients that need a real server
+ */
+pub async fn serve(router: Router) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    addr
+}
@@ -0,0 +1,285 @@
+use anyhow::{Result, anyhow};
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, request::Parts},
+};
+use serde::Deserialize;
+use std::{fmt, str::FromStr};
+use unicode_width::UnicodeWidthChar;
+
+use crate::{colors::ColorMode, error::ApiError};
+
+/// Header a CLI forwards its `NO_COLOR` env var in, see https://no-color.org
+pub const NO_COLOR_HEADER: &str = "no-color";
+
+/// Narrowest `?width=`, below which titles would be cut to a few characters
+pub const MIN_WIDTH: usize = 20;
+
+/// Widest `?width=`
+pub const MAX_WIDTH: usize = 1000;
+
+/// Columns a wrapped or truncated title keeps however little room the rest of its line leaves
+const MIN_TITLE_WIDTH: usize = 10;
+
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub enum Layout {
+    /// A labelled line per field
+    #[default]
+    Full,
+    /// A single line per stream
+    Compact,
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layout::Full => write!(f, "full"),
+            Layout::Compact => write!(f, "compact"),
+        }
+    }
+}
+
+impl FromStr for Layout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "full" => Ok(Layout::Full),
+            "compact" => Ok(Layout::Compact),
+            _ => Err(anyhow!("invalid layout: {s}, expected full or compact")),
+        }
+    }
+}
+
+/// How text pages are shown in the terminal
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub struct TextOptions {
+    pub color: ColorMode,
+    /// Columns of the terminal, titles being wrapped or truncated to fit when given
+    pub width: Option<usize>,
+    pub layout: Layout,
+}
+
+impl TextOptions {
+    /**
+     * Validates the `?color=`, `?width=` and `?layout=` parameters
+     */
+    pub fn parse(
+        color: Option<&str>,
+        width: Option<&str>,
+        layout: Option<&str>,
+    ) -> Result<Self, ApiError> {
+        let mut options = TextOptions::default();
+
+        if let Some(color) = color {
+            options.color = color.trim().parse().map_err(ApiError::bad_request)?;
+        }
+
+        if let Some(width) = width {
+            options.width = match width.trim().parse() {
+                Ok(width) if (MIN_WIDTH..=MAX_WIDTH).contains(&width) => Some(width),
+                _ => {
+                    return Err(ApiError::BadRequest(format!(
+                        "invalid width: {width}, expected {MIN_WIDTH} to {MAX_WIDTH}"
+                    )));
+                }
+            };
+        }
+
+        if let Some(layout) = layout {
+            options.layout = layout.trim().parse().map_err(ApiError::bad_request)?;
+        }
+
+        Ok(options)
+    }
+}
+
+/**
+ * Whether the client asked for no colours with a non-empty `No-Color` header
+ */
+pub fn no_color(headers: &HeaderMap) -> bool {
+    headers.get(NO_COLOR_HEADER).is_some_and(|val| !val.is_empty())
+}
+
+#[derive(Deserialize)]
+struct TextQuery {
+    color: Option<String>,
+    width: Option<String>,
+    layout: Option<String>,
+}
+
+/**
+ * The text options of the query, colours being turned off by `No-Color` unless `?color=` is given
+ */
+pub fn negotiate(query: Option<&str>, headers: &HeaderMap) -> Result<TextOptions, ApiError> {
+    let query = serde_urlencoded::from_str::<TextQuery>(query.unwrap_or_default())
+        .map_err(ApiError::bad_request)?;
+
+    let color = match query.color {
+        None if no_color(headers) => Some(ColorMode::Never.to_string()),
+        color => color,
+    };
+
+    TextOptions::parse(color.as_deref(), query.width.as_deref(), query.layout.as_deref())
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for TextOptions {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        negotiate(parts.uri.query(), &parts.headers)
+    }
+}
+
+/**
+ * Columns a string takes in a terminal, wide CJK characters taking two and escape codes none
+ */
+pub fn display_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // skips the parameters up to the final byte of the SGR sequence
+            chars.by_ref().find(|c| *c == 'm');
+            continue;
+        }
+
+        width += c.width().unwrap_or_default();
+    }
+
+    width
+}
+
+/**
+ * Room left for a title on a line of `width` columns once `used` are taken
+ */
+pub fn title_width(width: usize, used: usize) -> usize {
+    width.saturating_sub(used).max(MIN_TITLE_WIDTH)
+}
+
+/**
+ * Cuts text to `width` columns, marking the cut with an ellipsis
+ */
+pub fn truncate(text: &str, width: usize) -> String {
+    if display_width(text) <= width {
+        return text.to_string();
+    }
+
+    let mut truncated = String::new();
+    let mut used = 1;
+
+    for c in text.chars() {
+        used += c.width().unwrap_or_default();
+        if used > width {
+            break;
+        }
+        truncated.push(c);
+    }
+
+    truncated.push('…');
+    truncated
+}
+
+/**
+ * Splits text into lines of at most `width` columns, between words when possible.
+ * Words wider than a line, like runs of CJK text, are split between characters
+ */
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut used = 0;
+
+    for word in text.split_whitespace() {
+        let word_width = display_width(word);
+        let gap = usize::from(!line.is_empty());
+
+        if used + gap + word_width <= width {
+            if gap > 0 {
+                line.push(' ');
+            }
+            line.push_str(word);
+            used += gap + word_width;
+            continue;
+        }
+
+        if word_width <= width {
+            lines.push(std::mem::take(&mut line));
+            line.push_str(word);
+            used = word_width;
+            continue;
+        }
+
+        if gap > 0 && used + gap < width {
+            line.push(' ');
+            used += gap;
+        }
+
+        for c in word.chars() {
+            let char_width = c.width().unwrap_or_default();
+
+            if used + char_width > width {
+                lines.push(std::mem::take(&mut line));
+                used = 0;
+            }
+            line.push(c);
+            used += char_width;
+        }
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_display_width() {
+        assert_eq!(display_width("karaoke"), 7);
+        assert_eq!(display_width("【歌枠】"), 8);
+        assert_eq!(display_width("\x1b[38;5;196m[live]\x1b[0m"), 6);
+
+        assert_eq!(truncate("karaoke", 7), "karaoke");
+        assert_eq!(truncate("karaoke & chill", 10), "karaoke &…");
+        assert_eq!(truncate("【歌枠】雑談", 7), "【歌枠…");
+    }
+
+    #[test]
+    fn wraps_between_words_and_wide_characters() {
+        assert_eq!(wrap("karaoke & chill stream", 10), ["karaoke &", "chill", "stream"]);
+        assert_eq!(wrap("【歌枠】夏の思い出を歌う", 10), ["【歌枠】夏", "の思い出を", "歌う"]);
+        assert_eq!(wrap("live 【歌枠】夏", 10), ["live", "【歌枠】夏"]);
+        assert_eq!(wrap("live 【歌枠】夏の歌", 10), ["live 【歌", "枠】夏の歌"]);
+        assert_eq!(wrap("", 10), [""]);
+
+        for line in wrap("【3D LIVE】夏祭り with everyone！ありがとう", 12) {
+            assert!(display_width(&line) <= 12, "{line}");
+        }
+    }
+
+    #[test]
+    fn parses_text_options() {
+        let mut headers = HeaderMap::new();
+        headers.insert(NO_COLOR_HEADER, "1".parse().unwrap());
+
+        assert_eq!(negotiate(None, &HeaderMap::new()).unwrap(), TextOptions::default());
+        assert_eq!(negotiate(None, &headers).unwrap().color, ColorMode::Never);
+        assert_eq!(
+            negotiate(Some("color=truecolor"), &headers).unwrap().color,
+            ColorMode::TrueColor
+        );
+        assert_eq!(
+            negotiate(Some("width=80&layout=compact"), &headers).unwrap(),
+            TextOptions { color: ColorMode::Never, width: Some(80), layout: Layout::Compact }
+        );
+
+        for query in ["color=rainbow", "width=10", "width=wide", "layout=grid"] {
+            assert!(negotiate(Some(query), &HeaderMap::new()).is_err(), "{query}");
+        }
+    }
+}
@@ -1,105 +1,341 @@
-use chrono::{Duration, TimeDelta, Utc};
-use std::cmp::Ordering;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use chrono_tz::Tz;
+use std::{fmt, str::FromStr};
 
-const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+use crate::i18n::Locale;
 
-pub fn humanize(time: &str) -> (String, String) {
-    let tz = &chrono::Utc::now().timezone();
-    let parsed = chrono::DateTime::parse_from_rfc3339(time).unwrap();
+/**
+ * A date in `tz` and the time left until it, or since it, in the words of `locale`.
+ * A time that can't be parsed is shown as is, without the time left
+ */
+pub fn humanize(time: &str, tz: Tz, locale: Locale, precision: Precision) -> (String, String) {
+    let messages = locale.messages();
+    let Ok(parsed) = DateTime::parse_from_rfc3339(time) else {
+        return (time.to_string(), String::new());
+    };
+    let date = parsed.with_timezone(&tz).format(messages.date_format).to_string();
 
     let delta = parsed.signed_duration_since(Utc::now());
-    let periods = get_periods(delta);
+    let periods = get_periods(delta, precision);
+    let future = delta > TimeDelta::zero();
 
-    let text = periods
-        .iter()
-        .map(|period| -> String {
-            match period {
-                TimePeriod::Minutes(n) => format!("{n}m"),
-                TimePeriod::Hours(n) => format!("{n}h"),
-                TimePeriod::Days(n) => format!("{n}d"),
-                TimePeriod::Weeks(n) => format!("{n}w"),
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(" ");
+    let humanized = match periods.is_empty() {
+        true if future => messages.within_a_minute.into(),
+        true => messages.now.into(),
+        false => locale.relative(&locale.duration(&periods), future),
+    };
 
-    if text.is_empty() {
-        return (
-            parsed.with_timezone(tz).format(TIME_FORMAT).to_string(), //
-            "now".into(),
-        );
-    }
+    (date, humanized)
+}
 
-    let humanized = match delta.cmp(&TimeDelta::zero()) {
-        Ordering::Equal => "now".into(),
-        Ordering::Greater => format!("in {text}"),
-        Ordering::Less => format!("{text} ago"),
-    };
+/**
+ * Seconds from now until an RFC3339 time, negative once it has passed
+ */
+pub fn seconds_until(time: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|parsed| parsed.signed_duration_since(Utc::now()).num_seconds())
+}
 
-    (
-        parsed.with_timezone(tz).format(TIME_FORMAT).to_string(), //
-        humanized,
-    )
+/**
+ * An RFC3339 time as an RFC3339 time in `tz`
+ */
+pub fn localize(time: &str, tz: Tz) -> String {
+    match DateTime::parse_from_rfc3339(time) {
+        Ok(parsed) => parsed.with_timezone(&tz).to_rfc3339_opts(SecondsFormat::Secs, true),
+        Err(_) => time.to_string(),
+    }
 }
 
-#[derive(Clone, Copy, Debug)]
-enum TimePeriod {
+/**
+ * Parses an IANA time zone name like `Asia/Tokyo`
+ */
+pub fn parse_tz(name: &str) -> Result<Tz> {
+    name.parse().map_err(|_| anyhow!("unknown time zone: {name}, expected a name like Asia/Tokyo"))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimePeriod {
     Minutes(i64),
     Hours(i64),
     Days(i64),
     Weeks(i64),
 }
 
-fn get_periods(td: TimeDelta) -> Vec<TimePeriod> {
-    let mut periods = vec![];
+/// Length of a unit in seconds, and the period of that many units
+type Unit = (i64, fn(i64) -> TimePeriod);
+
+/// Units a duration is split into, largest first
+const UNITS: [Unit; 4] = [
+    (60 * 60 * 24 * 7, TimePeriod::Weeks),
+    (60 * 60 * 24, TimePeriod::Days),
+    (60 * 60, TimePeriod::Hours),
+    (60, TimePeriod::Minutes),
+];
 
-    let (weeks, remainder) = get_weeks(td);
-    if let Some(weeks) = weeks {
-        periods.push(TimePeriod::Weeks(weeks));
+/// How the last unit shown is rounded
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub enum Rounding {
+    #[default]
+    Floor,
+    Round,
+    Ceil,
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rounding::Floor => write!(f, "floor"),
+            Rounding::Round => write!(f, "round"),
+            Rounding::Ceil => write!(f, "ceil"),
+        }
     }
+}
+
+impl FromStr for Rounding {
+    type Err = anyhow::Error;
 
-    let (days, remainder) = get_days(remainder);
-    if let Some(days) = days {
-        periods.push(TimePeriod::Days(days));
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "floor" => Ok(Rounding::Floor),
+            "round" => Ok(Rounding::Round),
+            "ceil" => Ok(Rounding::Ceil),
+            _ => Err(anyhow!("invalid rounding: {s}, expected floor, round or ceil")),
+        }
     }
+}
+
+/// How many units of a duration are shown, `2w 3d 4h 12m` being `2w 3d` with two
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct Precision {
+    /// 1 to 4, counted from the largest unit of the duration
+    pub units: usize,
+    pub rounding: Rounding,
+}
 
-    let (hours, remainder) = get_hours(remainder);
-    if let Some(hours) = hours {
-        periods.push(TimePeriod::Hours(hours));
+impl Default for Precision {
+    fn default() -> Self {
+        Precision { units: UNITS.len(), rounding: Rounding::default() }
     }
+}
 
-    let (minutes, _) = get_minutes(remainder);
-    if let Some(minutes) = minutes {
-        periods.push(TimePeriod::Minutes(minutes));
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.rounding {
+            Rounding::Floor => write!(f, "{}", self.units),
+            rounding => write!(f, "{},{rounding}", self.units),
+        }
     }
+}
 
-    periods
+impl FromStr for Precision {
+    type Err = anyhow::Error;
+
+    /**
+     * Parses a number of units optionally followed by a rounding, like `2` or `1,ceil`
+     */
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (units, rounding) = match s.split_once(',') {
+            Some((units, rounding)) => (units.trim(), rounding.trim().parse()?),
+            None => (s.trim(), Rounding::default()),
+        };
+
+        match units.parse() {
+            Ok(units) if (1..=UNITS.len()).contains(&units) => Ok(Precision { units, rounding }),
+            _ => Err(anyhow!("invalid precision: {s}, expected 1 to {} units", UNITS.len())),
+        }
+    }
 }
 
-fn get_weeks(td: TimeDelta) -> (Option<i64>, TimeDelta) {
-    let weeks = td.num_weeks();
-    let remainder = td - Duration::weeks(weeks);
-    normalize(weeks, remainder)
+/**
+ * Splits a duration into `precision.units` units starting from its largest one,
+ * rounding the smallest, and leaving out units that come to zero.
+ * Durations under a minute have no units
+ */
+fn get_periods(td: TimeDelta, precision: Precision) -> Vec<TimePeriod> {
+    let seconds = td.num_seconds().abs();
+
+    let Some(largest) = UNITS.iter().position(|(length, _)| seconds >= *length) else {
+        return vec![];
+    };
+
+    let (step, _) = UNITS[(largest + precision.units - 1).min(UNITS.len() - 1)];
+    let steps = match precision.rounding {
+        Rounding::Floor => seconds / step,
+        Rounding::Round => (seconds + step / 2) / step,
+        Rounding::Ceil => (seconds + step - 1) / step,
+    };
+
+    // rounding up can carry into a larger unit, like 59m40s into 1h
+    let mut remainder = steps * step;
+
+    UNITS
+        .iter()
+        .filter_map(|(length, period)| {
+            let n = remainder / length;
+            remainder %= length;
+            (n > 0).then(|| period(n))
+        })
+        .collect()
 }
 
-fn get_days(td: TimeDelta) -> (Option<i64>, TimeDelta) {
-    let days = td.num_days();
-    let remainder = td - Duration::days(days);
-    normalize(days, remainder)
+/// A point in time given as a date or as an offset from now
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum TimeBound {
+    At(DateTime<Utc>),
+    /// Offset in seconds, resolved when the page is rendered
+    In(i64),
 }
 
-fn get_hours(td: TimeDelta) -> (Option<i64>, TimeDelta) {
-    let hours = td.num_hours();
-    let remainder = td - Duration::hours(hours);
-    normalize(hours, remainder)
+impl TimeBound {
+    pub fn resolve(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            TimeBound::At(time) => *time,
+            TimeBound::In(seconds) => now + TimeDelta::seconds(*seconds),
+        }
+    }
 }
 
-fn get_minutes(td: TimeDelta) -> (Option<i64>, TimeDelta) {
-    let minutes = td.num_minutes();
-    let remainder = td - Duration::minutes(minutes);
-    normalize(minutes, remainder)
+impl fmt::Display for TimeBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeBound::At(time) => f.write_str(&time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            TimeBound::In(seconds) => write!(f, "{seconds}s"),
+        }
+    }
 }
 
-fn normalize(wholes: impl Into<Option<i64>>, remainder: Duration) -> (Option<i64>, TimeDelta) {
-    (wholes.into().map(i64::abs).filter(|x| *x > 0), remainder)
+impl FromStr for TimeBound {
+    type Err = anyhow::Error;
+
+    /**
+     * Parses an RFC3339 date or a relative offset like `24h`, `1d12h` or `-30m`
+     */
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(TimeBound::At(time.with_timezone(&Utc)));
+        }
+
+        let invalid =
+            || anyhow!("invalid time: {s}, expected an RFC3339 date or an offset like 24h");
+
+        let (sign, offset) = match s.strip_prefix('-') {
+            Some(offset) => (-1, offset),
+            None => (1, s.strip_prefix('+').unwrap_or(s)),
+        };
+
+        if offset.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut seconds: i64 = 0;
+        let mut digits = String::new();
+
+        for c in offset.chars() {
+            if c.is_ascii_digit() {
+                digits.push(c);
+                continue;
+            }
+
+            let unit = match c {
+                's' => 1,
+                'm' => 60,
+                'h' => 60 * 60,
+                'd' => 60 * 60 * 24,
+                'w' => 60 * 60 * 24 * 7,
+                _ => return Err(invalid()),
+            };
+            let n: i64 = digits.parse().map_err(|_| invalid())?;
+
+            seconds =
+                n.checked_mul(unit).and_then(|n| seconds.checked_add(n)).ok_or_else(invalid)?;
+            digits.clear();
+        }
+
+        if !digits.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(TimeBound::In(sign * seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_in_time_zones() {
+        let tokyo = parse_tz("Asia/Tokyo").unwrap();
+        let time = "2026-01-01T12:00:00Z";
+
+        let precision = Precision::default();
+
+        assert_eq!(humanize(time, Tz::UTC, Locale::En, precision).0, "2026-01-01 12:00 UTC");
+        assert_eq!(humanize(time, tokyo, Locale::En, precision).0, "2026-01-01 21:00 JST");
+        assert_eq!(humanize(time, tokyo, Locale::Ja, precision).0, "2026年1月1日 21:00 JST");
+        assert_eq!(
+            humanize("tomorrow", tokyo, Locale::En, precision),
+            ("tomorrow".to_string(), String::new())
+        );
+        assert_eq!(localize(time, Tz::UTC), "2026-01-01T12:00:00Z");
+        assert_eq!(localize(time, tokyo), "2026-01-01T21:00:00+09:00");
+        assert!(parse_tz("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn rounds_to_precision() {
+        use TimePeriod::*;
+
+        let td = TimeDelta::weeks(2)
+            + TimeDelta::days(3)
+            + TimeDelta::hours(4)
+            + TimeDelta::minutes(12)
+            + TimeDelta::seconds(40);
+        let precision = |s: &str| s.parse::<Precision>().unwrap();
+
+        assert_eq!(
+            get_periods(td, Precision::default()),
+            [Weeks(2), Days(3), Hours(4), Minutes(12)]
+        );
+        assert_eq!(get_periods(-td, precision("2")), [Weeks(2), Days(3)]);
+        assert_eq!(
+            get_periods(td, precision("4,round")),
+            [Weeks(2), Days(3), Hours(4), Minutes(13)]
+        );
+        assert_eq!(get_periods(td, precision("1,ceil")), [Weeks(3)]);
+
+        let td = TimeDelta::minutes(59) + TimeDelta::seconds(40);
+        assert_eq!(get_periods(td, precision("1")), [Minutes(59)]);
+        assert_eq!(get_periods(td, precision("1,round")), [Hours(1)]);
+
+        assert!(get_periods(TimeDelta::seconds(59), precision("1,ceil")).is_empty());
+
+        for s in ["0", "5", "2,up", ""] {
+            assert!(s.parse::<Precision>().is_err(), "{s}");
+        }
+        for s in ["3", "1,ceil"] {
+            assert_eq!(precision(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn parses_time_bounds() {
+        let at: TimeBound = "2026-01-01T09:00:00+09:00".parse().unwrap();
+        assert_eq!(at, TimeBound::At("2026-01-01T00:00:00Z".parse().unwrap()));
+
+        assert_eq!("24h".parse::<TimeBound>().unwrap(), TimeBound::In(86400));
+        assert_eq!("1d12h".parse::<TimeBound>().unwrap(), TimeBound::In(129600));
+        assert_eq!("-30m".parse::<TimeBound>().unwrap(), TimeBound::In(-1800));
+
+        assert!("".parse::<TimeBound>().is_err());
+        assert!("24".parse::<TimeBound>().is_err());
+        assert!("h".parse::<TimeBound>().is_err());
+        assert!("tomorrow".parse::<TimeBound>().is_err());
+
+        for bound in [at, TimeBound::In(-1800)] {
+            assert_eq!(bound.to_string().parse::<TimeBound>().unwrap(), bound);
+        }
+    }
 }
@@ -0,0 +1,541 @@
+mod utils;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, TimeDelta, Utc};
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    api::{DbChannel, DbVideo, VideoKind},
+    config::CONFIG,
+    platform::{Platform, PlatformVideo, Platforms},
+    utils::get_description_excerpt,
+};
+
+use utils::{
+    AppTokenResponse, RawScheduleSegment, RawTwitchStream, RawTwitchUser, ScheduleApiResponse,
+    StreamApiResponse, UserApiResponse,
+};
+
+/// Number of schedule segments listed per channel
+const SCHEDULE_SEGMENTS: &str = "10";
+
+pub static TWITCH_CLIENT: LazyLock<Option<TwitchClient>> = LazyLock::new(|| {
+    let (Some(client_id), Some(client_secret)) =
+        (&CONFIG.twitch.client_id, &CONFIG.twitch.client_secret)
+    else {
+        return None;
+    };
+
+    Some(TwitchClient::new(
+        &CONFIG.twitch.api_url,
+        &CONFIG.twitch.auth_url,
+        client_id,
+        client_secret,
+    ))
+});
+
+fn get_client() -> Result<&'static TwitchClient> {
+    TWITCH_CLIENT.as_ref().ok_or(anyhow!("twitch is not configured"))
+}
+
+pub struct Twitch {}
+
+impl Platform for Twitch {
+    async fn get_channel(&self, channel_id: &str) -> Result<DbChannel> {
+        get_client()?.get_channel(channel_id).await
+    }
+
+    async fn get_stream_ids(&self, channel_id: &str) -> Result<Vec<String>> {
+        get_client()?.get_stream_ids(channel_id).await
+    }
+
+    async fn get_streams(&self, stream_ids: &[String]) -> Result<Vec<PlatformVideo>> {
+        get_client()?.get_streams(stream_ids).await
+    }
+}
+
+/**
+ * Parses `twitch:login` or a twitch.tv channel url
+ */
+pub fn parse_channel_login(input: &str) -> Option<String> {
+    let input = input.trim();
+
+    let login = match input.strip_prefix("twitch:") {
+        Some(login) => login,
+        None => input
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_start_matches("www.")
+            .trim_start_matches("m.")
+            .strip_prefix("twitch.tv/")?
+            .split(['/', '?', '#'])
+            .next()?,
+    };
+
+    let valid = !login.is_empty()
+        && login.len() <= 25
+        && login.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    valid.then(|| login.to_lowercase())
+}
+
+/**
+ * Stream ids are `<broadcaster id>:<segment or stream id>`,
+ * since the schedule endpoint can only be queried per broadcaster
+ */
+fn split_stream_id(stream_id: &str) -> Option<(&str, &str)> {
+    stream_id.split_once(':')
+}
+
+struct AppToken {
+    token: String,
+    expires_at: Instant,
+}
+
+/// Helix client using an app access token (client credentials flow)
+pub struct TwitchClient {
+    http: reqwest::Client,
+    api_url: String,
+    auth_url: String,
+    client_id: String,
+    client_secret: String,
+    token: Mutex<Option<AppToken>>,
+}
+
+impl TwitchClient {
+    pub fn new(api_url: &str, auth_url: &str, client_id: &str, client_secret: &str) -> Self {
+        TwitchClient {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("failed to build HTTP client"),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            auth_url: auth_url.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            token: Mutex::new(None),
+        }
+    }
+
+    async fn get_token(&self) -> Result<String> {
+        if let Some(token) = self.token.lock().unwrap().as_ref()
+            && token.expires_at > Instant::now()
+        {
+            return Ok(token.token.clone());
+        }
+
+        let response = self
+            .http
+            .post(&self.auth_url)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("grant_type", "client_credentials"),
+            ])
+            .send()
+            .await?;
+
+        if response.status().as_u16() != 200 {
+            return Err(anyhow!(response.status()));
+        }
+
+        let body: AppTokenResponse = response.json().await?;
+
+        // refresh a minute early so requests in flight don't use an expired token
+        let expires_in = Duration::from_secs(body.expires_in.saturating_sub(60));
+        *self.token.lock().unwrap() = Some(AppToken {
+            token: body.access_token.clone(),
+            expires_at: Instant::now() + expires_in,
+        });
+
+        Ok(body.access_token)
+    }
+
+    async fn get(&self, path: &str, query: &[(&str, &str)]) -> Result<reqwest::Response> {
+        let token = self.get_token().await?;
+
+        let response = self
+            .http
+            .get(format!("{}/{path}", self.api_url))
+            .query(query)
+            .header(ACCEPT, "application/json")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .header("Client-Id", &self.client_id)
+            .send()
+            .await?;
+
+        if response.status().as_u16() == 401 {
+            // the token was revoked, get a new one next time
+            self.token.lock().unwrap().take();
+        }
+
+        Ok(response)
+    }
+
+    async fn get_user(&self, query: &[(&str, &str)]) -> Result<RawTwitchUser> {
+        let response = self.get("users", query).await?;
+        if response.status().as_u16() != 200 {
+            return Err(anyhow!(response.status()));
+        }
+
+        let body: UserApiResponse = response.json().await?;
+
+        body.data.into_iter().next().ok_or(anyhow!("channel not found"))
+    }
+
+    pub async fn get_channel(&self, channel_id: &str) -> Result<DbChannel> {
+        Ok(self.get_user(&[("id", channel_id)]).await?.into())
+    }
+
+    pub async fn get_channel_by_login(&self, login: &str) -> Result<DbChannel> {
+        Ok(self.get_user(&[("login", login)]).await?.into())
+    }
+
+    async fn get_schedule(&self, broadcaster_id: &str) -> Result<Vec<RawScheduleSegment>> {
+        let response = self
+            .get("schedule", &[("broadcaster_id", broadcaster_id), ("first", SCHEDULE_SEGMENTS)])
+            .await?;
+
+        // channels without a schedule return a 404
+        if response.status().as_u16() == 404 {
+            return Ok(vec![]);
+        }
+
+        if response.status().as_u16() != 200 {
+            return Err(anyhow!(response.status()));
+        }
+
+        let body: ScheduleApiResponse = response.json().await?;
+
+        Ok(body
+            .data
+            .segments
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|segment| segment.canceled_until.is_none())
+            .collect())
+    }
+
+    async fn get_live_streams(&self, broadcaster_ids: &[&str]) -> Result<Vec<RawTwitchStream>> {
+        let mut streams = vec![];
+
+        for chunk in broadcaster_ids.chunks(100) {
+            let query: Vec<_> = chunk.iter().map(|id| ("user_id", *id)).collect();
+
+            let response = self.get("streams", &query).await?;
+            if response.status().as_u16() != 200 {
+                return Err(anyhow!(response.status()));
+            }
+
+            let body: StreamApiResponse = response.json().await?;
+            streams.extend(body.data);
+        }
+
+        Ok(streams)
+    }
+
+    pub async fn get_stream_ids(&self, broadcaster_id: &str) -> Result<Vec<String>> {
+        let segments = self.get_schedule(broadcaster_id).await?;
+        let live = self.get_live_streams(&[broadcaster_id]).await?;
+
+        let mut stream_ids: Vec<String> =
+            segments.iter().map(|segment| format!("{broadcaster_id}:{}", segment.id)).collect();
+
+        // unscheduled streams are tracked by their stream id
+        for stream in live {
+            if get_live_segment(&segments).is_none() {
+                stream_ids.push(format!("{broadcaster_id}:{}", stream.id));
+            }
+        }
+
+        Ok(stream_ids)
+    }
+
+    pub async fn get_streams(&self, stream_ids: &[String]) -> Result<Vec<PlatformVideo>> {
+        let mut requested: HashMap<&str, Vec<&str>> = HashMap::new();
+        for stream_id in stream_ids {
+            if let Some((broadcaster_id, id)) = split_stream_id(stream_id) {
+                requested.entry(broadcaster_id).or_default().push(id);
+            }
+        }
+
+        let broadcaster_ids: Vec<&str> = requested.keys().copied().collect();
+        let live = self.get_live_streams(&broadcaster_ids).await?;
+
+        let mut videos = vec![];
+
+        for (broadcaster_id, ids) in requested {
+            let segments = self.get_schedule(broadcaster_id).await?;
+            let stream = live.iter().find(|stream| stream.user_id == broadcaster_id);
+            let live_segment = stream.and(get_live_segment(&segments)).map(|s| &s.id);
+
+            for id in ids {
+                if let Some(segment) = segments.iter().find(|segment| segment.id == id) {
+                    let stream = stream.filter(|_| live_segment == Some(&segment.id));
+                    videos.push(segment_to_video(broadcaster_id, segment, stream));
+                } else if let Some(stream) = stream.filter(|stream| stream.id == id) {
+                    videos.push(stream_to_video(stream));
+                }
+            }
+        }
+
+        Ok(videos)
+    }
+}
+
+/**
+ * Finds the schedule segment a stream going live right now belongs to
+ */
+fn get_live_segment(segments: &[RawScheduleSegment]) -> Option<&RawScheduleSegment> {
+    let now = Utc::now();
+
+    // streams often go live a bit before the scheduled time
+    let tolerance = TimeDelta::hours(1);
+
+    segments.iter().find(|segment| {
+        let Ok(start) = DateTime::parse_from_rfc3339(&segment.start_time) else {
+            return false;
+        };
+
+        let ended = segment
+            .end_time
+            .as_deref()
+            .and_then(|end| DateTime::parse_from_rfc3339(end).ok())
+            .is_some_and(|end| end < now);
+
+        start.with_timezone(&Utc) - tolerance <= now && !ended
+    })
+}
+
+fn get_thumbnails(stream: Option<&RawTwitchStream>) -> BTreeMap<String, String> {
+    stream
+        .and_then(|stream| stream.thumbnail_url.as_deref())
+        .map(|url| {
+            BTreeMap::from([
+                ("medium".to_string(), url.replace("{width}x{height}", "320x180")),
+                ("high".to_string(), url.replace("{width}x{height}", "480x360")),
+                ("maxres".to_string(), url.replace("{width}x{height}", "1280x720")),
+            ])
+        })
+        .unwrap_or_default()
+}
+
+fn segment_to_video(
+    broadcaster_id: &str,
+    segment: &RawScheduleSegment,
+    stream: Option<&RawTwitchStream>,
+) -> PlatformVideo {
+    let duration = segment.end_time.as_deref().and_then(|end| {
+        let start = DateTime::parse_from_rfc3339(&segment.start_time).ok()?;
+        let end = DateTime::parse_from_rfc3339(end).ok()?;
+        Some((end - start).num_seconds())
+    });
+
+    PlatformVideo {
+        video: DbVideo {
+            id: format!("{broadcaster_id}:{}", segment.id),
+            channel_id: broadcaster_id.to_string(),
+            channel_name: None,
+            channel_handle: None,
+            platform: Platforms::Twitch,
+            title: stream.map_or(segment.title.clone(), |stream| stream.title.clone()),
+            description: None,
+            thumbnails: get_thumbnails(stream),
+            live_broadcast_content: if stream.is_some() { "live" } else { "upcoming" }.to_string(),
+            concurrent_viewers: stream.map(|stream| stream.viewer_count),
+            duration,
+            premiere: false,
+            kind: VideoKind::Live,
+            collab: false,
+            scheduled_time: segment.start_time.clone(),
+            start_time: stream.map(|stream| stream.started_at.clone()),
+            end_time: None,
+        },
+        description: String::new(),
+    }
+}
+
+fn stream_to_video(stream: &RawTwitchStream) -> PlatformVideo {
+    PlatformVideo {
+        video: DbVideo {
+            id: format!("{}:{}", stream.user_id, stream.id),
+            channel_id: stream.user_id.clone(),
+            channel_name: None,
+            channel_handle: None,
+            platform: Platforms::Twitch,
+            title: stream.title.clone(),
+            description: None,
+            thumbnails: get_thumbnails(Some(stream)),
+            live_broadcast_content: "live".to_string(),
+            concurrent_viewers: Some(stream.viewer_count),
+            duration: None,
+            premiere: false,
+            kind: VideoKind::Live,
+            collab: false,
+            scheduled_time: stream.started_at.clone(),
+            start_time: Some(stream.started_at.clone()),
+            end_time: None,
+        },
+        description: String::new(),
+    }
+}
+
+impl From<RawTwitchUser> for DbChannel {
+    fn from(val: RawTwitchUser) -> Self {
+        DbChannel {
+            id: val.id,
+            name: val.display_name,
+            platform: Platforms::Twitch,
+            handle: Some(val.login),
+            description: get_description_excerpt(&val.description),
+            country: None,
+            thumbnails: val
+                .profile_image_url
+                .map(|url| BTreeMap::from([("default".to_string(), url)]))
+                .unwrap_or_default(),
+            banner: val.offline_image_url.filter(|url| !url.is_empty()),
+            subscriber_count: None,
+            disabled: 0,
+            watched: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Json, Router, extract::Query, http::HeaderMap, routing::get, routing::post};
+    use chrono::{TimeDelta, Utc};
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::testing;
+
+    fn authorized(headers: &HeaderMap) -> bool {
+        headers.get("authorization").is_some_and(|v| v == "Bearer stub-token")
+            && headers.get("client-id").is_some_and(|v| v == "stub-client")
+    }
+
+    /**
+     * Serves canned Helix responses for broadcaster 1001, who is live right now
+     */
+    async fn spawn_stub() -> TwitchClient {
+        let now = Utc::now();
+        let live_start = (now - TimeDelta::minutes(10)).to_rfc3339();
+        let live_end = (now + TimeDelta::hours(2)).to_rfc3339();
+        let next_start = (now + TimeDelta::days(2)).to_rfc3339();
+
+        let router = Router::new()
+            .route(
+                "/oauth2/token",
+                post(|| async { Json(json!({ "access_token": "stub-token", "expires_in": 3600 })) }),
+            )
+            .route(
+                "/helix/users",
+                get(|headers: HeaderMap, query: Query<HashMap<String, String>>| async move {
+                    assert!(authorized(&headers));
+                    let matches = query.get("id").map(String::as_str) == Some("1001")
+                        || query.get("login").map(String::as_str) == Some("furi");
+
+                    let data: Vec<Value> = match matches {
+                        true => vec![json!({
+                            "id": "1001",
+                            "login": "furi",
+                            "display_name": "Furi",
+                            "description": "hello",
+                            "profile_image_url": "https://example.com/avatar.png",
+                            "offline_image_url": ""
+                        })],
+                        false => vec![],
+                    };
+
+                    Json(json!({ "data": data }))
+                }),
+            )
+            .route(
+                "/helix/schedule",
+                get(move |headers: HeaderMap| async move {
+                    assert!(authorized(&headers));
+                    Json(json!({ "data": { "segments": [
+                        { "id": "seg-live", "start_time": live_start, "end_time": live_end, "title": "scheduled", "canceled_until": null },
+                        { "id": "seg-next", "start_time": next_start, "end_time": null, "title": "next time", "canceled_until": null },
+                        { "id": "seg-canceled", "start_time": next_start, "end_time": null, "title": "canceled", "canceled_until": next_start }
+                    ] } }))
+                }),
+            )
+            .route(
+                "/helix/streams",
+                get(|headers: HeaderMap| async move {
+                    assert!(authorized(&headers));
+                    Json(json!({ "data": [{
+                        "id": "5555",
+                        "user_id": "1001",
+                        "title": "live now",
+                        "viewer_count": 42,
+                        "started_at": (Utc::now() - TimeDelta::minutes(5)).to_rfc3339(),
+                        "thumbnail_url": "https://example.com/live-{width}x{height}.jpg"
+                    }] }))
+                }),
+            );
+
+        let addr = testing::serve(router).await;
+
+        TwitchClient::new(
+            &format!("http://{addr}/helix"),
+            &format!("http://{addr}/oauth2/token"),
+            "stub-client",
+            "stub-secret",
+        )
+    }
+
+    #[test]
+    fn parses_channel_logins() {
+        assert_eq!(parse_channel_login("twitch:Furi"), Some("furi".to_string()));
+        assert_eq!(parse_channel_login("https://www.twitch.tv/furi/schedule"), Some("furi".into()));
+        assert_eq!(parse_channel_login("twitch.tv/furi?ref=x"), Some("furi".to_string()));
+        assert_eq!(parse_channel_login("@furi"), None);
+        assert_eq!(parse_channel_login("https://www.youtube.com/@furi"), None);
+    }
+
+    #[tokio::test]
+    async fn fetches_channels() {
+        let client = spawn_stub().await;
+
+        let channel = client.get_channel("1001").await.unwrap();
+        assert_eq!(channel.name, "Furi");
+        assert_eq!(channel.handle.as_deref(), Some("furi"));
+        assert_eq!(channel.platform, Platforms::Twitch);
+        assert_eq!(channel.banner, None);
+
+        let channel = client.get_channel_by_login("furi").await.unwrap();
+        assert_eq!(channel.id, "1001");
+
+        assert!(client.get_channel("404").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn lists_and_fetches_streams() {
+        let client = spawn_stub().await;
+
+        let stream_ids = client.get_stream_ids("1001").await.unwrap();
+        assert_eq!(stream_ids, vec!["1001:seg-live", "1001:seg-next"]);
+
+        let videos = client.get_streams(&stream_ids).await.unwrap();
+        assert_eq!(videos.len(), 2);
+
+        let live = &videos.iter().find(|v| v.video.id == "1001:seg-live").unwrap().video;
+        assert_eq!(live.title, "live now");
+        assert_eq!(live.concurrent_viewers, Some(42));
+        assert!(live.start_time.is_some());
+        assert_eq!(live.thumbnails["maxres"], "https://example.com/live-1280x720.jpg");
+
+        let next = &videos.iter().find(|v| v.video.id == "1001:seg-next").unwrap().video;
+        assert_eq!(next.title, "next time");
+        assert_eq!(next.start_time, None);
+        assert_eq!(next.live_broadcast_content, "upcoming");
+    }
+}
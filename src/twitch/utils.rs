@@ -0,0 +1,65 @@
+use serde::Deserialize;
+
+// ----- Auth -----
+
+#[derive(Deserialize, Debug)]
+pub struct AppTokenResponse {
+    pub access_token: String,
+    pub expires_in: u64,
+}
+
+// ----- Users -----
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RawTwitchUser {
+    pub id: String,
+    pub login: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub description: String,
+    pub profile_image_url: Option<String>,
+    pub offline_image_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserApiResponse {
+    pub data: Vec<RawTwitchUser>,
+}
+
+// ----- Schedule -----
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RawScheduleSegment {
+    pub id: String,
+    pub start_time: String,
+    pub end_time: Option<String>,
+    pub title: String,
+    pub canceled_until: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RawSchedule {
+    pub segments: Option<Vec<RawScheduleSegment>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ScheduleApiResponse {
+    pub data: RawSchedule,
+}
+
+// ----- Streams -----
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RawTwitchStream {
+    pub id: String,
+    pub user_id: String,
+    pub title: String,
+    pub viewer_count: i64,
+    pub started_at: String,
+    pub thumbnail_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StreamApiResponse {
+    pub data: Vec<RawTwitchStream>,
+}
@@ -1,29 +1,14 @@
-use axum::http::HeaderValue;
+const DESCRIPTION_EXCERPT_LENGTH: usize = 200;
 
-pub fn is_term(user_agent: Option<&HeaderValue>) -> bool {
-    let user_agent = match user_agent {
-        Some(ua) => match ua.to_str() {
-            Ok(s) => s,
-            Err(_) => {
-                tracing::debug!("invalid user agent header");
-                return false;
-            }
-        },
-        None => {
-            tracing::debug!("no user agent provided");
-            return false;
-        }
-    };
+pub fn get_description_excerpt(description: &str) -> Option<String> {
+    let description = description.trim();
 
-    if user_agent.contains("curl") {
-        return true;
+    if description.is_empty() {
+        return None;
     }
 
-    if user_agent.starts_with("Wget") {
-        return true;
+    match description.char_indices().nth(DESCRIPTION_EXCERPT_LENGTH) {
+        Some((end, _)) => Some(format!("{}…", description[..end].trim_end())),
+        None => Some(description.to_string()),
     }
-
-    tracing::debug!("user agent: {}", user_agent);
-
-    false
 }
@@ -1,38 +1,213 @@
 use anyhow::{Ok, Result, anyhow};
 use reqwest::header::ACCEPT;
+use std::fmt;
 
 use crate::config::CONFIG;
 
-use super::{HTTP_CLIENT, YoutubeChannel, utils::ChannelApiResponse};
+use super::{HTTP_CLIENT, YoutubeChannel, send, utils::ChannelApiResponse};
 
-pub async fn get_channel_api(channel_id: &str) -> Result<YoutubeChannel> {
-    let url = format!(
-        "https://www.googleapis.com/youtube/v3/channels?part=id,snippet&key={}&id={}",
-        CONFIG.youtube.apikey, channel_id
-    );
-
-    let response = HTTP_CLIENT //
-        .get(url)
-        .header(ACCEPT, "application/json")
-        .send()
-        .await?;
-    if response.status().as_u16() != 200 {
-        return Err(anyhow!(response.status()));
+/// The different ways a channel can be referenced in config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelRef {
+    /// `UC...`, `youtube.com/channel/UC...`
+    Id(String),
+    /// `@handle`, `youtube.com/@handle`
+    Handle(String),
+    /// `youtube.com/c/name` (legacy custom url)
+    Custom(String),
+    /// `youtube.com/user/name` (legacy username)
+    Username(String),
+}
+
+impl fmt::Display for ChannelRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelRef::Id(id) => write!(f, "{id}"),
+            ChannelRef::Handle(handle) => write!(f, "@{handle}"),
+            ChannelRef::Custom(name) => write!(f, "c/{name}"),
+            ChannelRef::Username(name) => write!(f, "user/{name}"),
+        }
+    }
+}
+
+impl ChannelRef {
+    /**
+     * Key used to cache the resolved channel id
+     */
+    pub fn cache_key(&self) -> String {
+        self.to_string().to_lowercase()
     }
+}
 
-    let body: ChannelApiResponse = response.json().await?;
+fn is_channel_id(s: &str) -> bool {
+    s.len() == 24
+        && s.starts_with("UC")
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/**
+ * Parses a channel id, handle or channel url
+ */
+pub fn parse_channel_ref(input: &str) -> Option<ChannelRef> {
+    let input = input.trim();
+
+    if is_channel_id(input) {
+        return Some(ChannelRef::Id(input.to_string()));
+    }
+
+    if let Some(handle) = input.strip_prefix('@') {
+        return (!handle.is_empty()).then(|| ChannelRef::Handle(handle.to_string()));
+    }
+
+    let url = input
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("www.")
+        .trim_start_matches("m.");
+
+    let path = url.strip_prefix("youtube.com")?;
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let mut segments = path.split('/').filter(|s| !s.is_empty());
 
-    let items = body.items.unwrap_or_default();
-    if items.is_empty() {
-        return Err(anyhow!("channel not found"));
+    match (segments.next()?, segments.next()) {
+        (handle, _) if handle.starts_with('@') && handle.len() > 1 => {
+            Some(ChannelRef::Handle(handle[1..].to_string()))
+        }
+        ("channel", Some(id)) if is_channel_id(id) => Some(ChannelRef::Id(id.to_string())),
+        ("c", Some(name)) => Some(ChannelRef::Custom(name.to_string())),
+        ("user", Some(name)) => Some(ChannelRef::Username(name.to_string())),
+        _ => None,
     }
+}
+
+pub async fn get_channel_api(channel_id: &str) -> Result<YoutubeChannel> {
+    let channels = get_channels_api(&[("id", channel_id)]).await?;
 
-    let raw_channel = items[0].to_owned();
+    channels.into_iter().next().ok_or(anyhow!("channel not found"))
+}
 
-    let channel = YoutubeChannel {
-        id: raw_channel.id, //
-        name: raw_channel.snippet.title,
+/**
+ * Looks up the channel a reference points to,
+ * failing if it doesn't exist or matches more than one channel
+ */
+pub async fn resolve_channel(channel_ref: &ChannelRef) -> Result<YoutubeChannel> {
+    let channels = match channel_ref {
+        ChannelRef::Id(id) => get_channels_api(&[("id", id)]).await?,
+        ChannelRef::Handle(handle) => get_channels_api(&[("forHandle", handle)]).await?,
+        ChannelRef::Username(name) => get_channels_api(&[("forUsername", name)]).await?,
+        ChannelRef::Custom(name) => {
+            // custom urls aren't exposed by the API, they usually match either the handle or the username
+            let mut channels = get_channels_api(&[("forHandle", name)]).await?;
+            for channel in get_channels_api(&[("forUsername", name)]).await? {
+                if !channels.iter().any(|c| c.id == channel.id) {
+                    channels.push(channel);
+                }
+            }
+            channels
+        }
     };
 
-    Ok(channel)
+    match channels.len() {
+        0 => Err(anyhow!("channel {channel_ref} not found")),
+        1 => Ok(channels.into_iter().next().unwrap()),
+        _ => Err(anyhow!(
+            "channel {channel_ref} is ambiguous: {}",
+            channels.iter().map(|c| c.id.as_str()).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+async fn get_channels_api(filter: &[(&str, &str)]) -> Result<Vec<YoutubeChannel>> {
+    let request = HTTP_CLIENT //
+        .get("https://www.googleapis.com/youtube/v3/channels")
+        .query(&[
+            ("part", "id,snippet,statistics,brandingSettings"),
+            ("key", &CONFIG.youtube.apikey),
+        ])
+        .query(filter)
+        .header(ACCEPT, "application/json");
+    let response = send("channels", request).await?;
+
+    let body: ChannelApiResponse = response.json().await?;
+
+    let channels = body
+        .items
+        .unwrap_or_default()
+        .into_iter()
+        .map(|raw_channel| YoutubeChannel {
+            id: raw_channel.id, //
+            name: raw_channel.snippet.title,
+            handle: raw_channel.snippet.custom_url,
+            description: raw_channel.snippet.description,
+            country: raw_channel.snippet.country,
+            thumbnails: raw_channel
+                .snippet
+                .thumbnails
+                .into_iter()
+                .map(|(size, thumbnail)| (size, thumbnail.url))
+                .collect(),
+            banner: raw_channel
+                .branding_settings
+                .and_then(|branding| branding.image)
+                .and_then(|image| image.banner_external_url),
+            subscriber_count: raw_channel
+                .statistics
+                .filter(|stats| !stats.hidden_subscriber_count)
+                .and_then(|stats| stats.subscriber_count)
+                .and_then(|n| n.parse().ok()),
+        })
+        .collect();
+
+    Ok(channels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_channel_refs() {
+        let id = "UCMwGHR0BTZuLsmjY_NT5Pwg";
+        let cases = [
+            (id, Some(ChannelRef::Id(id.to_string()))),
+            (" UCMwGHR0BTZuLsmjY_NT5Pwg ", Some(ChannelRef::Id(id.to_string()))),
+            (
+                "https://www.youtube.com/channel/UCMwGHR0BTZuLsmjY_NT5Pwg",
+                Some(ChannelRef::Id(id.to_string())),
+            ),
+            (
+                "youtube.com/channel/UCMwGHR0BTZuLsmjY_NT5Pwg/videos",
+                Some(ChannelRef::Id(id.to_string())),
+            ),
+            ("@ninomaeinanis", Some(ChannelRef::Handle("ninomaeinanis".to_string()))),
+            (
+                "https://youtube.com/@ninomaeinanis",
+                Some(ChannelRef::Handle("ninomaeinanis".to_string())),
+            ),
+            (
+                "https://m.youtube.com/@ninomaeinanis/live?si=x",
+                Some(ChannelRef::Handle("ninomaeinanis".to_string())),
+            ),
+            (
+                "https://www.youtube.com/c/NinomaeInanis",
+                Some(ChannelRef::Custom("NinomaeInanis".to_string())),
+            ),
+            (
+                "http://youtube.com/user/inanis#about",
+                Some(ChannelRef::Username("inanis".to_string())),
+            ),
+            // too short or not a channel
+            ("UCMwGHR0BTZuLsmjY", None),
+            ("https://youtube.com/channel/UC123", None),
+            ("@", None),
+            ("https://youtube.com/@", None),
+            ("https://youtube.com/watch?v=dQw4w9WgXcQ", None),
+            ("https://twitch.tv/ninomaeinanis", None),
+            ("ninomaeinanis", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_channel_ref(input), expected, "{input}");
+        }
+    }
 }
@@ -1,11 +1,18 @@
 pub mod channels;
 mod utils;
 pub mod videos;
+pub mod websub;
 mod xml;
+use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
-use std::{sync::LazyLock, time::Duration};
+use std::{collections::BTreeMap, sync::LazyLock, time::Duration};
 
-use crate::api::{DbChannel, DbVideo};
+use crate::{
+    api::{DbChannel, DbVideo, VideoKind},
+    metrics,
+    platform::{Platform, PlatformVideo, Platforms},
+    utils::get_description_excerpt,
+};
 
 pub static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
     reqwest::Client::builder()
@@ -14,10 +21,54 @@ pub static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
         .expect("failed to build HTTP client")
 });
 
+/**
+ * Sends a request to YouTube, failing on error statuses, and counts it under `endpoint`
+ */
+async fn send(endpoint: &str, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+    let result = match request.send().await {
+        Ok(response) if response.status().is_success() => Ok(response),
+        Ok(response) => Err(anyhow!(response.status())),
+        Err(e) => Err(e.into()),
+    };
+
+    metrics::record_youtube_call(endpoint, result.is_ok());
+    result
+}
+
+pub struct Youtube {}
+
+impl Platform for Youtube {
+    async fn get_channel(&self, channel_id: &str) -> Result<DbChannel> {
+        Ok(channels::get_channel_api(channel_id).await?.into())
+    }
+
+    async fn get_stream_ids(&self, channel_id: &str) -> Result<Vec<String>> {
+        videos::get_video_ids_xml(channel_id).await
+    }
+
+    async fn get_streams(&self, stream_ids: &[String]) -> Result<Vec<PlatformVideo>> {
+        let videos = videos::get_videos_api(stream_ids).await?;
+
+        Ok(videos
+            .iter()
+            .map(|video| PlatformVideo {
+                video: video.into(), //
+                description: video.description.clone(),
+            })
+            .collect())
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct YoutubeChannel {
     pub id: String,
     pub name: String,
+    pub handle: Option<String>,
+    pub description: String,
+    pub country: Option<String>,
+    pub thumbnails: BTreeMap<String, String>,
+    pub banner: Option<String>,
+    pub subscriber_count: Option<i64>,
 }
 
 impl From<YoutubeChannel> for DbChannel {
@@ -25,7 +76,15 @@ impl From<YoutubeChannel> for DbChannel {
         DbChannel {
             id: val.id,
             name: val.name,
+            platform: Platforms::Youtube,
+            handle: val.handle,
+            description: get_description_excerpt(&val.description),
+            country: val.country,
+            thumbnails: val.thumbnails,
+            banner: val.banner,
+            subscriber_count: val.subscriber_count,
             disabled: 0, // Default to disabled
+            watched: 0,
         }
     }
 }
@@ -35,6 +94,12 @@ pub struct YoutubeVideo {
     pub id: String,
     pub channel_id: String,
     pub title: String,
+    pub description: String,
+    pub thumbnails: BTreeMap<String, String>,
+    pub live_broadcast_content: String,
+    pub concurrent_viewers: Option<i64>,
+    pub duration: Option<i64>,
+    pub premiere: bool,
     pub scheduled_time: String,
     pub start_time: Option<String>,
     pub end_time: Option<String>,
@@ -46,7 +111,17 @@ impl From<&YoutubeVideo> for DbVideo {
             id: val.id.clone(),
             channel_id: val.channel_id.clone(),
             channel_name: None, // Channel name is not stored in the video
+            channel_handle: None,
+            platform: Platforms::Youtube,
             title: val.title.clone(),
+            description: get_description_excerpt(&val.description),
+            thumbnails: val.thumbnails.clone(),
+            live_broadcast_content: val.live_broadcast_content.clone(),
+            concurrent_viewers: val.concurrent_viewers,
+            duration: val.duration,
+            premiere: val.premiere,
+            kind: VideoKind::default(), // Tagged once fetched, see `platform::classify`
+            collab: false,              // Set when read back for a participant
             scheduled_time: val.scheduled_time.clone(),
             start_time: val.start_time.clone(),
             end_time: val.end_time.clone(),
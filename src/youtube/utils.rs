@@ -1,4 +1,12 @@
 use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// ----- Shared -----
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Thumbnail {
+    pub url: String,
+}
 
 // ----- Videos -----
 
@@ -7,6 +15,11 @@ use serde::Deserialize;
 pub struct VideoSnippet {
     pub title: String,
     pub channel_id: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub thumbnails: BTreeMap<String, Thumbnail>,
+    pub live_broadcast_content: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -15,6 +28,13 @@ pub struct VideoLiveStreamingDetails {
     pub actual_start_time: Option<String>,
     pub actual_end_time: Option<String>,
     pub scheduled_start_time: Option<String>,
+    pub concurrent_viewers: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoContentDetails {
+    pub duration: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -22,6 +42,7 @@ pub struct VideoLiveStreamingDetails {
 pub struct RawYoutubeVideo {
     pub id: String,
     pub snippet: VideoSnippet,
+    pub content_details: Option<VideoContentDetails>,
     pub live_streaming_details: Option<VideoLiveStreamingDetails>,
 }
 
@@ -36,6 +57,32 @@ pub struct VideoApiResponse {
 #[serde(rename_all = "camelCase")]
 pub struct ChannelSnippet {
     pub title: String,
+    pub custom_url: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    pub country: Option<String>,
+    #[serde(default)]
+    pub thumbnails: BTreeMap<String, Thumbnail>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelStatistics {
+    pub subscriber_count: Option<String>,
+    #[serde(default)]
+    pub hidden_subscriber_count: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelBrandingImage {
+    pub banner_external_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelBrandingSettings {
+    pub image: Option<ChannelBrandingImage>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -43,9 +90,45 @@ pub struct ChannelSnippet {
 pub struct RawYoutubeChannel {
     pub id: String,
     pub snippet: ChannelSnippet,
+    pub statistics: Option<ChannelStatistics>,
+    pub branding_settings: Option<ChannelBrandingSettings>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct ChannelApiResponse {
     pub items: Option<Vec<RawYoutubeChannel>>,
 }
+
+// ----- Helpers -----
+
+/**
+ * Parses an ISO 8601 duration (e.g. `PT1H2M3S`) into seconds
+ */
+pub fn parse_duration(duration: &str) -> Option<i64> {
+    let rest = duration.strip_prefix('P')?;
+    let mut seconds = 0;
+    let mut number = String::new();
+    let mut in_time = false;
+
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            unit => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+
+                seconds += match (unit, in_time) {
+                    ('W', false) => n * 604_800,
+                    ('D', false) => n * 86_400,
+                    ('H', true) => n * 3_600,
+                    ('M', true) => n * 60,
+                    ('S', true) => n,
+                    _ => return None,
+                };
+            }
+        }
+    }
+
+    number.is_empty().then_some(seconds)
+}
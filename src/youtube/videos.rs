@@ -1,11 +1,11 @@
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use reqwest::header::{ACCEPT, USER_AGENT};
 
 use crate::config::CONFIG;
 
 use super::{
-    HTTP_CLIENT, YoutubeVideo,
-    utils::{RawYoutubeVideo, VideoApiResponse},
+    HTTP_CLIENT, YoutubeVideo, send,
+    utils::{RawYoutubeVideo, VideoApiResponse, parse_duration},
     xml,
 };
 
@@ -20,20 +20,15 @@ pub async fn get_videos_api(video_ids: &[String]) -> Result<Vec<YoutubeVideo>> {
 
     for chunk in video_ids.chunks(50) {
         let url = format!(
-            "https://www.googleapis.com/youtube/v3/videos?part=snippet,liveStreamingDetails&key={}&id={}",
+            "https://www.googleapis.com/youtube/v3/videos?part=snippet,contentDetails,liveStreamingDetails&key={}&id={}",
             CONFIG.youtube.apikey,
             chunk.join(",")
         );
 
-        let response = HTTP_CLIENT //
+        let request = HTTP_CLIENT //
             .get(url)
-            .header(ACCEPT, "application/json")
-            .send()
-            .await?;
-
-        if response.status().as_u16() != 200 {
-            return Err(anyhow!(response.status()));
-        }
+            .header(ACCEPT, "application/json");
+        let response = send("videos", request).await?;
 
         let body: VideoApiResponse = response.json().await?;
 
@@ -48,41 +43,47 @@ pub async fn get_videos_api(video_ids: &[String]) -> Result<Vec<YoutubeVideo>> {
 }
 
 pub async fn get_video_ids_xml(channel_id: &str) -> Result<Vec<String>> {
-    let response = HTTP_CLIENT
+    let request = HTTP_CLIENT
         .get(format!("https://www.youtube.com/feeds/videos.xml?channel_id={channel_id}"))
-        .header(USER_AGENT, WEB_USER_AGENT)
-        .send()
-        .await?;
-
-    if response.status().as_u16() != 200 {
-        return Err(anyhow!(response.status()));
-    }
+        .header(USER_AGENT, WEB_USER_AGENT);
+    let response = send("feed", request).await?;
 
     let body = response.text().await?;
-    let document = roxmltree::Document::parse(&body)?;
-    let mut video_ids = Vec::<String>::new();
-
-    for entry_node in document.descendants() {
-        if entry_node.has_tag_name("entry") {
-            let video_id = xml::get_property(&entry_node, "videoId");
 
-            if let Some(video_id) = video_id {
-                video_ids.push(video_id);
-            }
-        }
-    }
-
-    Ok(video_ids)
+    xml::get_video_ids(&body)
 }
 
 fn process_raw_video(raw_video: RawYoutubeVideo) -> Option<YoutubeVideo> {
     if let Some(live) = raw_video.live_streaming_details {
         // Only care about live streams
         if let Some(scheduled_time) = live.scheduled_start_time {
+            let duration = raw_video
+                .content_details
+                .and_then(|details| details.duration)
+                .and_then(|duration| parse_duration(&duration));
+
+            // Live streams report a zero duration until they end,
+            // premieres are uploaded ahead of time and already have one
+            let premiere = live.actual_end_time.is_none() && duration.is_some_and(|d| d > 0);
+
             return Some(YoutubeVideo {
                 id: raw_video.id.clone(),
                 channel_id: raw_video.snippet.channel_id.clone(),
                 title: raw_video.snippet.title,
+                description: raw_video.snippet.description,
+                thumbnails: raw_video
+                    .snippet
+                    .thumbnails
+                    .into_iter()
+                    .map(|(size, thumbnail)| (size, thumbnail.url))
+                    .collect(),
+                live_broadcast_content: raw_video
+                    .snippet
+                    .live_broadcast_content
+                    .unwrap_or("none".to_string()),
+                concurrent_viewers: live.concurrent_viewers.and_then(|n| n.parse().ok()),
+                duration,
+                premiere,
                 scheduled_time,
                 start_time: live.actual_start_time,
                 end_time: live.actual_end_time,
@@ -0,0 +1,364 @@
+use anyhow::{Result, anyhow};
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::get,
+};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha1::Sha1;
+
+use crate::{
+    config::{CONFIG, WebsubConfig},
+    scheduler, sqlite,
+};
+
+use super::{HTTP_CLIENT, send, xml};
+
+const TOPIC_PREFIX: &str = "https://www.youtube.com/xml/feeds/videos.xml?channel_id=";
+
+pub fn topic_url(channel_id: &str) -> String {
+    format!("{TOPIC_PREFIX}{channel_id}")
+}
+
+pub fn get_topic_channel_id(topic: &str) -> Option<&str> {
+    topic.strip_prefix(TOPIC_PREFIX)
+}
+
+#[derive(Clone)]
+struct WebsubState {
+    secret: String,
+    /// Called with the ids of the videos a notification is about
+    notify: fn(Vec<String>),
+}
+
+#[derive(Deserialize)]
+struct Verification {
+    #[serde(rename = "hub.mode")]
+    mode: String,
+    #[serde(rename = "hub.topic")]
+    topic: String,
+    #[serde(rename = "hub.challenge")]
+    challenge: String,
+    #[serde(rename = "hub.lease_seconds")]
+    lease_seconds: Option<u64>,
+}
+
+/**
+ * The callback the hub verifies subscriptions with and sends notifications to
+ */
+pub fn router(secret: String) -> Router {
+    routes(WebsubState { secret, notify: check_videos })
+}
+
+fn routes(state: WebsubState) -> Router {
+    Router::new().route("/websub", get(get_websub).post(post_websub)).with_state(state)
+}
+
+fn check_videos(video_ids: Vec<String>) {
+    tokio::spawn(async move {
+        if let Err(e) = scheduler::check_notified_videos(video_ids).await {
+            tracing::error!("failed to check notified videos: {e}");
+        }
+    });
+}
+
+// GET /websub
+async fn get_websub(query: Query<Verification>) -> (StatusCode, String) {
+    let Some(channel_id) = get_topic_channel_id(&query.topic) else {
+        return (StatusCode::NOT_FOUND, String::new());
+    };
+
+    let tracked = [sqlite::get_db_channels(), sqlite::get_db_watched_channels()]
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|c| c.id == channel_id);
+
+    // only the subscriptions we want are confirmed, so others can't change them for us
+    match (query.mode.as_str(), tracked) {
+        ("subscribe", true) => {
+            tracing::info!(
+                "verified websub subscription for {} (lease: {:?}s)",
+                channel_id,
+                query.lease_seconds
+            );
+            (StatusCode::OK, query.challenge.clone())
+        }
+        ("unsubscribe", false) => {
+            tracing::info!("verified websub unsubscription for {}", channel_id);
+            (StatusCode::OK, query.challenge.clone())
+        }
+        (mode, _) => {
+            tracing::info!("rejecting websub {} for {}", mode, channel_id);
+            (StatusCode::NOT_FOUND, String::new())
+        }
+    }
+}
+
+// POST /websub
+async fn post_websub(
+    State(state): State<WebsubState>,
+    req_headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let signature =
+        req_headers.get("x-hub-signature").and_then(|val| val.to_str().ok()).unwrap_or_default();
+
+    // the hub expects a 2xx even for notifications we ignore
+    if !verify_signature(&state.secret, &body, signature) {
+        tracing::warn!("ignoring websub notification with an invalid signature");
+        return StatusCode::NO_CONTENT;
+    }
+
+    let video_ids = match std::str::from_utf8(&body)
+        .map_err(anyhow::Error::from)
+        .and_then(get_video_ids_notification)
+    {
+        Ok(video_ids) => video_ids,
+        Err(e) => {
+            tracing::warn!("failed to parse websub notification: {e}");
+            return StatusCode::NO_CONTENT;
+        }
+    };
+
+    if !video_ids.is_empty() {
+        (state.notify)(video_ids);
+    }
+
+    StatusCode::NO_CONTENT
+}
+
+/**
+ * Asks the hub to (re)subscribe the callback to a channel's feed
+ */
+pub async fn subscribe(channel_id: &str) -> Result<()> {
+    post_subscription(&CONFIG.websub, channel_id).await
+}
+
+async fn post_subscription(config: &WebsubConfig, channel_id: &str) -> Result<()> {
+    let (Some(callback_url), Some(secret)) = (&config.callback_url, &config.secret) else {
+        return Err(anyhow!("websub callback url or secret is not configured"));
+    };
+
+    let topic = topic_url(channel_id);
+    let lease_seconds = config.lease_seconds.to_string();
+
+    let form = [
+        ("hub.callback", callback_url.as_str()),
+        ("hub.mode", "subscribe"),
+        ("hub.topic", topic.as_str()),
+        ("hub.verify", "async"),
+        ("hub.lease_seconds", lease_seconds.as_str()),
+        ("hub.secret", secret.as_str()),
+    ];
+
+    let request = HTTP_CLIENT //
+        .post(&config.hub_url)
+        .form(&form);
+    send("websub", request).await?;
+
+    Ok(())
+}
+
+/**
+ * Checks an `X-Hub-Signature` header (`sha1=<hex>`) against the body
+ */
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(digest) = signature.strip_prefix("sha1=") else {
+        return false;
+    };
+
+    let Ok(digest) = hex::decode(digest) else {
+        return false;
+    };
+
+    let Ok(mut mac) = Hmac::<Sha1>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+
+    mac.update(body);
+    mac.verify_slice(&digest).is_ok()
+}
+
+pub fn get_video_ids_notification(body: &str) -> Result<Vec<String>> {
+    xml::get_video_ids(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Form, body::Body, http::Request, routing::post};
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{api::DbChannel, testing};
+
+    const SECRET: &str = "key";
+    const BODY: &[u8] = b"The quick brown fox jumps over the lazy dog";
+
+    #[test]
+    fn verifies_signatures() {
+        let valid = "sha1=de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9";
+        assert!(verify_signature(SECRET, BODY, valid));
+
+        assert!(!verify_signature("other", BODY, valid));
+        assert!(!verify_signature(SECRET, b"another body", valid));
+        assert!(!verify_signature(SECRET, BODY, "sha1=de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d8"));
+        assert!(!verify_signature(SECRET, BODY, "sha1=not-hex"));
+
+        // a missing header is passed as an empty signature
+        assert!(!verify_signature(SECRET, BODY, ""));
+
+        let sha256 = "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8";
+        assert!(!verify_signature(SECRET, BODY, sha256));
+        assert!(!verify_signature(SECRET, BODY, "de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9"));
+    }
+
+    #[test]
+    fn parses_notifications() {
+        let published = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns:yt="http://www.youtube.com/xml/schemas/2015" xmlns="http://www.w3.org/2005/Atom">
+  <link rel="hub" href="https://pubsubhubbub.appspot.com"/>
+  <title>YouTube video feed</title>
+  <entry>
+    <id>yt:video:VIDEO_ID</id>
+    <yt:videoId>VIDEO_ID</yt:videoId>
+    <yt:channelId>UCb8dLvDvmZ-d92KEy_9oWog</yt:channelId>
+    <title>【歌枠】karaoke</title>
+    <published>2026-01-01T12:00:00+00:00</published>
+  </entry>
+</feed>"#;
+        assert_eq!(get_video_ids_notification(published).unwrap(), ["VIDEO_ID"]);
+
+        let deleted = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns:at="http://purl.org/atompub/tombstones/1.0" xmlns="http://www.w3.org/2005/Atom">
+  <at:deleted-entry ref="yt:video:VIDEO_ID" when="2026-01-01T12:00:00+00:00"/>
+</feed>"#;
+        assert!(get_video_ids_notification(deleted).unwrap().is_empty());
+
+        assert!(get_video_ids_notification("<feed><entry>").is_err());
+    }
+
+    static NOTIFIED: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+    fn test_routes() -> Router {
+        routes(WebsubState {
+            secret: SECRET.to_string(),
+            notify: |video_ids| NOTIFIED.lock().unwrap().extend(video_ids),
+        })
+    }
+
+    async fn verify(mode: &str, channel_id: &str) -> (StatusCode, String) {
+        let query = serde_urlencoded::to_string([
+            ("hub.mode", mode),
+            ("hub.topic", &topic_url(channel_id)),
+            ("hub.challenge", "challenge"),
+            ("hub.lease_seconds", "3600"),
+        ])
+        .unwrap();
+
+        let req = Request::get(format!("/websub?{query}")).body(Body::empty()).unwrap();
+        let res = test_routes().oneshot(req).await.unwrap();
+        let status = res.status();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    async fn notify(body: &str, signature: Option<&str>) -> StatusCode {
+        let mut req = Request::post("/websub");
+        if let Some(signature) = signature {
+            req = req.header("x-hub-signature", signature);
+        }
+
+        let req = req.body(Body::from(body.to_string())).unwrap();
+        test_routes().oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn verifies_tracked_subscriptions() {
+        sqlite::init_db();
+
+        let tracked = "UCwebsubtracked000000000";
+        sqlite::upsert_db_channel(DbChannel {
+            id: tracked.to_string(),
+            name: "Tracked".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let untracked = "UCwebsubuntracked0000000";
+
+        assert_eq!(verify("subscribe", tracked).await, (StatusCode::OK, "challenge".into()));
+        assert_eq!(verify("subscribe", untracked).await.0, StatusCode::NOT_FOUND);
+        // nobody else can unsubscribe us from a channel we track
+        assert_eq!(verify("unsubscribe", tracked).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(verify("unsubscribe", untracked).await, (StatusCode::OK, "challenge".into()));
+        assert_eq!(verify("denied", tracked).await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn checks_signed_notifications_only() {
+        let body = r#"<feed xmlns:yt="http://www.youtube.com/xml/schemas/2015" xmlns="http://www.w3.org/2005/Atom">
+  <entry><yt:videoId>NOTIFIED_ID</yt:videoId></entry>
+</feed>"#;
+
+        let mut mac = Hmac::<Sha1>::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(body.as_bytes());
+        let signature = format!("sha1={}", hex::encode(mac.finalize().into_bytes()));
+
+        // ignored, but still acknowledged so the hub doesn't retry
+        assert_eq!(notify(body, None).await, StatusCode::NO_CONTENT);
+        assert_eq!(notify(body, Some("sha1=00")).await, StatusCode::NO_CONTENT);
+        assert!(NOTIFIED.lock().unwrap().is_empty());
+
+        assert_eq!(notify(body, Some(&signature)).await, StatusCode::NO_CONTENT);
+        assert_eq!(*NOTIFIED.lock().unwrap(), ["NOTIFIED_ID"]);
+    }
+
+    #[tokio::test]
+    async fn posts_subscriptions_to_the_hub() {
+        let received: Arc<Mutex<Option<HashMap<String, String>>>> = Arc::default();
+
+        let router = Router::new()
+            .route(
+                "/subscribe",
+                post(|State(received): State<Arc<Mutex<_>>>, Form(form)| async move {
+                    *received.lock().unwrap() = Some(form);
+                    StatusCode::ACCEPTED
+                }),
+            )
+            .with_state(received.clone());
+        let addr = testing::serve(router).await;
+
+        let mut config = WebsubConfig {
+            hub_url: format!("http://{addr}/subscribe"),
+            callback_url: Some("https://oshi.example.com/websub".to_string()),
+            secret: Some(SECRET.to_string()),
+            lease_seconds: 3600,
+        };
+        post_subscription(&config, "UCwebsubhub0000000000000").await.unwrap();
+
+        let form = received.lock().unwrap().take().unwrap();
+        let expected = [
+            ("hub.callback", "https://oshi.example.com/websub"),
+            ("hub.mode", "subscribe"),
+            ("hub.topic", &topic_url("UCwebsubhub0000000000000")),
+            ("hub.verify", "async"),
+            ("hub.lease_seconds", "3600"),
+            ("hub.secret", SECRET),
+        ];
+        assert_eq!(
+            form,
+            expected.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>()
+        );
+
+        config.secret = None;
+        assert!(post_subscription(&config, "UCwebsubhub0000000000000").await.is_err());
+    }
+}
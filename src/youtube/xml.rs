@@ -1,3 +1,4 @@
+use anyhow::Result;
 use roxmltree::Node;
 
 pub fn get_property(node: &Node, property: &str) -> Option<String> {
@@ -9,3 +10,23 @@ pub fn get_property(node: &Node, property: &str) -> Option<String> {
 
     None
 }
+
+/**
+ * Collects the video ids of every entry in an Atom feed
+ */
+pub fn get_video_ids(body: &str) -> Result<Vec<String>> {
+    let document = roxmltree::Document::parse(body)?;
+    let mut video_ids = Vec::<String>::new();
+
+    for entry_node in document.descendants() {
+        if entry_node.has_tag_name("entry") {
+            let video_id = get_property(&entry_node, "videoId");
+
+            if let Some(video_id) = video_id {
+                video_ids.push(video_id);
+            }
+        }
+    }
+
+    Ok(video_ids)
+}